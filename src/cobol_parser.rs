@@ -1,66 +1,373 @@
 use anyhow::{Result, bail};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::copybook::CopybookResolver;
+use crate::diagnostics::{Diagnostic, Diagnostics, DiagnosticsError};
+use crate::picture::{PictureCategory, parse_picture};
+use crate::source_format::{SourceEntry, SourceFormat, check_indicators, join_entries, read_source};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CobolField {
     pub level: u32,
     pub name: String,
+    pub redefines: Option<String>,
     pub picture: Option<String>,
     pub data_type: Option<String>,
     pub occurs: Option<u32>,
+    pub occurs_min: Option<u32>,
+    pub depending_on: Option<String>,
+    /// ASCENDING/DESCENDING KEY phrases of a table, in the order written.
+    pub keys: Vec<TableKey>,
+    /// Index names from INDEXED BY, which take no storage in the record.
+    pub indexed_by: Vec<String>,
+    pub sign: Option<SignClause>,
+    pub synchronized: Option<Synchronized>,
+    pub justified: bool,
+    pub blank_when_zero: bool,
+    /// The VALUE literal as written, such as `ZEROS`, `'N'` or `ALL '*'`.
+    pub value: Option<String>,
+    pub external: bool,
+    pub global: bool,
     pub children: Vec<CobolField>,
     pub line_number: usize,
+    /// Copybook the entry was copied from, or `None` for the main input.
+    pub source_file: Option<String>,
+    /// Level-88 condition names declared under the item; they take no storage.
+    pub conditions: Vec<ConditionName>,
+    /// Level-66 aliases declared at the end of a record; they take no storage.
+    pub renames: Vec<Renames>,
+    /// DATA DIVISION section a record is described in, when the input names one.
+    pub section: Option<DataSection>,
+    /// FD or SD a record is described under.
+    pub file_description: Option<String>,
+}
+
+/// A key data item a table is ordered on, for SEARCH ALL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableKey {
+    pub ascending: bool,
+    pub name: String,
+}
+
+/// Where a SIGN clause puts the operational sign of a signed numeric DISPLAY item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignClause {
+    pub position: SignPosition,
+    /// The sign takes a character of its own instead of sharing a digit's.
+    pub separate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignPosition {
+    Leading,
+    Trailing,
+}
+
+/// A SYNCHRONIZED clause, which aligns a binary item on its natural boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Synchronized {
+    Plain,
+    Left,
+    Right,
+}
+
+/// A level-88 condition name and the values for which it is true.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConditionName {
+    pub name: String,
+    pub values: Vec<ConditionValue>,
+    pub line_number: usize,
+}
+
+/// A level-66 entry regrouping the items `from` through `thru` under a new name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Renames {
+    pub name: String,
+    pub from: String,
+    /// Last item of the range, or `None` when the alias renames a single item.
+    pub thru: Option<String>,
+    pub line_number: usize,
+}
+
+/// One entry of a condition's VALUE clause: a literal, or a THRU range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConditionValue {
+    pub from: String,
+    pub thru: Option<String>,
+}
+
+impl CobolField {
+    /// Whether the item is a FILLER, written as such or left unnamed.
+    pub fn is_filler(&self) -> bool {
+        self.name.eq_ignore_ascii_case("FILLER")
+    }
+}
+
+/// A DATA DIVISION section whose records can be laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataSection {
+    File,
+    WorkingStorage,
+    LocalStorage,
+    Linkage,
+}
+
+impl DataSection {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "FILE" => Some(DataSection::File),
+            "WORKING-STORAGE" => Some(DataSection::WorkingStorage),
+            "LOCAL-STORAGE" => Some(DataSection::LocalStorage),
+            "LINKAGE" => Some(DataSection::Linkage),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DataSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataSection::File => "FILE",
+            DataSection::WorkingStorage => "WORKING-STORAGE",
+            DataSection::LocalStorage => "LOCAL-STORAGE",
+            DataSection::Linkage => "LINKAGE",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    /// An FD: a file whose records are read and written.
+    File,
+    /// An SD: a sort or merge work file.
+    Sort,
+}
+
+/// An FD or SD entry of the FILE SECTION.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDescription {
+    pub kind: FileKind,
+    pub name: String,
+    pub line_number: usize,
+    /// Copybook the entry was copied from, or `None` for the main input.
+    pub source_file: Option<String>,
+    /// Record sizes from RECORD CONTAINS or RECORD IS VARYING.
+    pub record_contains: Option<RecordContains>,
+    pub block_contains: Option<BlockContains>,
+    /// RECORDING MODE as written, such as `F` or `V`.
+    pub recording_mode: Option<String>,
+    /// LABEL RECORDS, `STANDARD` or `OMITTED`.
+    pub label_records: Option<String>,
+}
+
+/// The record sizes a file's RECORD clause declares, in characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordContains {
+    /// Shortest record, when the clause gives a range.
+    pub min: Option<usize>,
+    /// Longest record, or the only size of fixed-length records.
+    pub max: Option<usize>,
+    /// Item holding the length of each record of a varying-size file.
+    pub depending_on: Option<String>,
+    /// Written as RECORD IS VARYING, so the sizes are bounds even without FROM.
+    #[serde(default)]
+    pub varying: bool,
+}
+
+/// The BLOCK CONTAINS clause, which sets the physical block size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockContains {
+    pub min: Option<usize>,
+    pub max: usize,
+    pub unit: BlockUnit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockUnit {
+    Characters,
+    Records,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CobolStructure {
     pub name: String,
+    /// The FD and SD entries records were described under, in input order.
+    pub files: Vec<FileDescription>,
     pub root_fields: Vec<CobolField>,
+    /// Warnings found while parsing that did not stop the layout.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-pub fn parse_cobol_structure(content: &str) -> Result<CobolStructure> {
-    let lines: Vec<&str> = content.lines().collect();
+/// Options controlling how COBOL source is read.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub source_format: SourceFormat,
+    /// The file being parsed, so relative COPY names resolve next to it.
+    pub source_path: Option<PathBuf>,
+    /// Directories searched for copybooks named by COPY statements.
+    pub include_dirs: Vec<PathBuf>,
+    /// Fail on warnings as well as errors.
+    pub strict: bool,
+}
+
+/// Parses the data description entries in `content`. Every problem found is
+/// collected; the parse fails with all of them when any is an error, or any
+/// at all in strict mode, and otherwise the warnings come back with the
+/// structure.
+pub fn parse_cobol_structure(content: &str, options: &ParseOptions) -> Result<CobolStructure> {
+    let lines = read_source(content, options.source_format);
     let mut structure = CobolStructure {
         name: String::new(),
+        files: Vec::new(),
         root_fields: Vec::new(),
+        diagnostics: Vec::new(),
     };
-
-    // Check for unsupported features
-    check_unsupported_features(content)?;
+    let mut diagnostics = Diagnostics::new(options.strict);
+    let main_file = options.source_path.as_ref().map(|path| path.display().to_string());
+    check_indicators(&lines, main_file.as_deref(), &mut diagnostics);
 
     let mut field_stack: Vec<CobolField> = Vec::new();
     let mut current_level = 0u32;
+    let mut section: Option<DataSection> = None;
+    let mut skip_section = false;
+    let mut file_description: Option<String> = None;
 
-    for (line_num, line) in lines.iter().enumerate() {
-        let line = line.trim_start();
+    let resolver = CopybookResolver::new(&options.include_dirs, options.source_format);
+    let entries = resolver.expand(data_division(join_entries(&lines)), options.source_path.as_deref(), &mut diagnostics);
+
+    for entry in entries {
+        let line = entry.text.as_str();
+        let file = entry.file.as_deref().or(main_file.as_deref());
+        let (line_number, column) = (entry.line_number, Some(entry.column));
+
+        if !entry.terminated {
+            diagnostics.warning(file, line_number, column, "missing period at the end of the entry");
+        }
         
-        // Skip comments, blank lines, and FD declarations
-        if line.is_empty() || line.starts_with('*') || line.trim_start_matches(char::is_whitespace).starts_with("fd ") {
+        // Section headers say what the records that follow are
+        if let Some(name) = section_name(line) {
+            section = DataSection::from_name(&name);
+            skip_section = section.is_none();
+            file_description = None;
+            if skip_section {
+                diagnostics.warning(file, line_number, column, format!("{} SECTION is not laid out", name));
+            }
+            continue;
+        }
+        if skip_section {
             continue;
         }
 
-        // Try to parse as a COBOL field definition
-        if let Some(field) = parse_cobol_line(line, line_num + 1)? {
-            if structure.name.is_empty() && field.level == 1 {
-                structure.name = field.name.clone();
+        // The FD or SD itself takes no storage; its records follow it
+        let words = split_clauses(line);
+        let kind = match words.first().map(|w| w.to_uppercase()).as_deref() {
+            Some("FD") => Some(FileKind::File),
+            Some("SD") => Some(FileKind::Sort),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            match parse_file_description(&words, kind, line_number) {
+                Ok(mut description) => {
+                    description.source_file = entry.file.clone();
+                    file_description = Some(description.name.clone());
+                    structure.files.push(description);
+                }
+                Err(err) => {
+                    diagnostics.error(file, line_number, column, err.to_string());
+                    file_description = words.get(1).cloned();
+                }
             }
+            section = Some(DataSection::File);
+            continue;
+        }
 
-            // Handle field hierarchy
-            while current_level >= field.level && !field_stack.is_empty() {
-                let completed_field = field_stack.pop().unwrap();
-                if field_stack.is_empty() {
-                    structure.root_fields.push(completed_field);
-                } else {
-                    let parent = field_stack.last_mut().unwrap();
-                    parent.children.push(completed_field);
+        // Condition names belong to the item they follow and are not part of the hierarchy
+        match parse_condition_line(line, line_number) {
+            Ok(Some(condition)) => {
+                match field_stack.last_mut() {
+                    Some(owner) => owner.conditions.push(condition),
+                    None => diagnostics.error(
+                        file,
+                        line_number,
+                        column,
+                        format!("condition {} does not follow a data item", condition.name),
+                    ),
                 }
-                current_level = field_stack.last().map(|f| f.level).unwrap_or(0);
+                continue;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                diagnostics.error(file, line_number, column, err.to_string());
+                continue;
             }
+        }
+
+        // RENAMES entries close the record's description and regroup items already in it
+        match parse_renames_line(line, line_number) {
+            Ok(Some(renames)) => {
+                while field_stack.len() > 1 {
+                    let completed_field = field_stack.pop().unwrap();
+                    field_stack.last_mut().unwrap().children.push(completed_field);
+                }
+                match field_stack.last_mut() {
+                    Some(record) => {
+                        current_level = hierarchy_level(record.level);
+                        record.renames.push(renames);
+                    }
+                    None => diagnostics.error(
+                        file,
+                        line_number,
+                        column,
+                        format!("{} does not follow a record description", renames.name),
+                    ),
+                }
+                continue;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                diagnostics.error(file, line_number, column, err.to_string());
+                continue;
+            }
+        }
 
-            current_level = field.level;
-            field_stack.push(field);
+        // Try to parse as a COBOL field definition
+        let mut field = match parse_cobol_line(line, line_number) {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                diagnostics.warning(file, line_number, column, format!("unrecognized entry: {}", line));
+                continue;
+            }
+            Err(err) => {
+                diagnostics.error(file, line_number, column, err.to_string());
+                continue;
+            }
+        };
+        field.source_file = entry.file.clone();
+        if structure.name.is_empty() && field.level == 1 {
+            structure.name = field.name.clone();
         }
+
+        // Handle field hierarchy
+        let level = hierarchy_level(field.level);
+        while current_level >= level && !field_stack.is_empty() {
+            let completed_field = field_stack.pop().unwrap();
+            if field_stack.is_empty() {
+                structure.root_fields.push(completed_field);
+            } else {
+                let parent = field_stack.last_mut().unwrap();
+                parent.children.push(completed_field);
+            }
+            current_level = field_stack.last().map(|f| hierarchy_level(f.level)).unwrap_or(0);
+        }
+
+        if field_stack.is_empty() {
+            field.section = section;
+            field.file_description = file_description.clone();
+        }
+        current_level = level;
+        field_stack.push(field);
     }
 
     // Close remaining fields
@@ -73,55 +380,748 @@ pub fn parse_cobol_structure(content: &str) -> Result<CobolStructure> {
         }
     }
 
+    for field in &mut structure.root_fields {
+        inherit_usage(field, None);
+        inherit_sign(field, None);
+    }
+    check_record_redefines(&structure.root_fields, &mut diagnostics, main_file.as_deref());
+    for field in &structure.root_fields {
+        resolve_renames(field, &mut diagnostics, main_file.as_deref());
+        check_hierarchy(field, &mut diagnostics, main_file.as_deref());
+        check_usage(field, &mut diagnostics, main_file.as_deref());
+        check_redefined_tables(field, &mut diagnostics, main_file.as_deref());
+    }
+
+    if diagnostics.failed() {
+        return Err(DiagnosticsError(diagnostics.into_sorted()).into());
+    }
+    structure.diagnostics = diagnostics.into_sorted();
+
     Ok(structure)
 }
 
-fn check_unsupported_features(content: &str) -> Result<()> {
-    let lower_content = content.to_lowercase();
-    
-    if lower_content.contains("varying in size") {
-        bail!("ERROR: variable length files not implemented");
+fn parse_cobol_line(line: &str, line_number: usize) -> Result<Option<CobolField>> {
+    // A data description entry is a level number and a name followed by clauses
+    // in any order:
+    //   level-number field-name [REDEFINES name] [IS EXTERNAL] [IS GLOBAL]
+    //   [PIC [IS] picture-string] [[USAGE [IS]] COMP-3|BINARY|DISPLAY|...]
+    //   [OCCURS [n TO] m [TIMES] [DEPENDING [ON] name] [ASCENDING|DESCENDING [KEY] [IS] name ...]
+    //    [INDEXED [BY] index-name ...]] [SIGN [IS] LEADING|TRAILING [SEPARATE]]
+    //   [SYNC [LEFT|RIGHT]] [JUST [RIGHT]] [BLANK [WHEN] ZERO] [VALUE [IS] literal] [.]
+    // The name may be omitted, which makes the item a FILLER.
+    let tokens = split_clauses(line);
+    let (Some(level_token), Some(second)) = (tokens.first(), tokens.get(1)) else {
+        return Ok(None);
+    };
+    if !level_token.starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    if !Regex::new(r"^\d{1,2}$")?.is_match(level_token) {
+        bail!("bad level number {}", level_token);
+    }
+    let (name, first_clause) = if is_clause_keyword(second) {
+        ("FILLER".to_string(), 1)
+    } else if second.eq_ignore_ascii_case("FILLER") {
+        ("FILLER".to_string(), 2)
+    } else if Regex::new(r"^[A-Za-z0-9][\w-]*$")?.is_match(second) && second.chars().any(|c| c.is_ascii_alphabetic()) {
+        (second.clone(), 2)
+    } else {
+        bail!("{} is not a valid data name", second);
+    };
+
+    let mut field = CobolField {
+        level: level_token.parse()?,
+        name,
+        line_number,
+        ..Default::default()
+    };
+
+    let mut clauses = tokens[first_clause..].iter().map(String::as_str).peekable();
+    while let Some(token) = clauses.next() {
+        match token.to_uppercase().as_str() {
+            "REDEFINES" => field.redefines = clauses.next().map(str::to_string),
+            "PIC" | "PICTURE" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is"));
+                field.picture = clauses.next().map(str::to_string);
+            }
+            "USAGE" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is"));
+                field.data_type = clauses.next().map(normalize_usage);
+            }
+            "OCCURS" => {
+                let Some(first) = clauses.next().and_then(|t| t.parse::<u32>().ok()) else {
+                    bail!("OCCURS of {} needs an integer", field.name);
+                };
+                // OCCURS n TO m keeps the maximum in `occurs`, which is what storage is allocated for
+                if clauses.next_if(|t| t.eq_ignore_ascii_case("to")).is_some() {
+                    let Some(max) = clauses.next().and_then(|t| t.parse::<u32>().ok()) else {
+                        bail!("OCCURS {} TO of {} needs an integer", first, field.name);
+                    };
+                    field.occurs_min = Some(first);
+                    field.occurs = Some(max);
+                } else {
+                    field.occurs = Some(first);
+                }
+                clauses.next_if(|t| t.eq_ignore_ascii_case("times"));
+            }
+            "ASCENDING" | "DESCENDING" => {
+                let ascending = token.eq_ignore_ascii_case("ascending");
+                clauses.next_if(|t| t.eq_ignore_ascii_case("key"));
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is"));
+                let names = table_names(&mut clauses);
+                if names.is_empty() {
+                    bail!("KEY of {} needs a data name", field.name);
+                }
+                field.keys.extend(names.into_iter().map(|name| TableKey { ascending, name }));
+            }
+            "INDEXED" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("by"));
+                let names = table_names(&mut clauses);
+                if names.is_empty() {
+                    bail!("INDEXED BY of {} needs an index name", field.name);
+                }
+                field.indexed_by.extend(names);
+            }
+            "SIGN" | "LEADING" | "TRAILING" => {
+                // SIGN IS may be left out: LEADING SEPARATE alone is a complete clause
+                let mut placement = token.to_uppercase();
+                if placement == "SIGN" {
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("is"));
+                    placement = clauses.next().unwrap_or_default().to_uppercase();
+                }
+                let position = match placement.as_str() {
+                    "LEADING" => SignPosition::Leading,
+                    "TRAILING" => SignPosition::Trailing,
+                    _ => bail!("SIGN of {} needs LEADING or TRAILING", field.name),
+                };
+                let separate = clauses.next_if(|t| t.eq_ignore_ascii_case("separate")).is_some();
+                if separate {
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("character"));
+                }
+                field.sign = Some(SignClause { position, separate });
+            }
+            "SYNC" | "SYNCHRONIZED" => {
+                field.synchronized = Some(match clauses.next_if(|t| t.eq_ignore_ascii_case("left") || t.eq_ignore_ascii_case("right")) {
+                    Some(side) if side.eq_ignore_ascii_case("left") => Synchronized::Left,
+                    Some(_) => Synchronized::Right,
+                    None => Synchronized::Plain,
+                });
+            }
+            "DEPENDING" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("on"));
+                field.depending_on = clauses.next().map(str::to_string);
+            }
+            "VALUE" | "VALUES" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is") || t.eq_ignore_ascii_case("are"));
+                let Some(first) = clauses.next() else {
+                    bail!("VALUE of {} needs a literal", field.name);
+                };
+                let mut literal = first.to_string();
+                if first.eq_ignore_ascii_case("all") {
+                    if let Some(repeated) = clauses.next() {
+                        literal = format!("{} {}", first, repeated);
+                    }
+                }
+                // Literals may be concatenated with &
+                while clauses.next_if(|t| *t == "&").is_some() {
+                    let Some(next) = clauses.next() else {
+                        bail!("VALUE of {} ends with &", field.name);
+                    };
+                    literal = format!("{} & {}", literal, next);
+                }
+                field.value = Some(literal);
+            }
+            "JUST" | "JUSTIFIED" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("right"));
+                field.justified = true;
+            }
+            "BLANK" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("when"));
+                if !clauses.next().is_some_and(|t| matches!(t.to_uppercase().as_str(), "ZERO" | "ZEROS" | "ZEROES")) {
+                    bail!("BLANK of {} needs WHEN ZERO", field.name);
+                }
+                field.blank_when_zero = true;
+            }
+            "EXTERNAL" => field.external = true,
+            "GLOBAL" => field.global = true,
+            "IS" => {}
+            word if is_usage(word) => field.data_type = Some(normalize_usage(word)),
+            // Guessing past a word we do not know could misplace every item after it
+            _ => bail!("unrecognized clause {} in the entry for {}", token, field.name),
+        }
+    }
+
+    Ok(Some(field))
+}
+
+/// Reads an FD or SD entry: `words` holds FD or SD, the file name and the
+/// clauses that follow it.
+fn parse_file_description(words: &[String], kind: FileKind, line_number: usize) -> Result<FileDescription> {
+    let Some(name) = words.get(1) else {
+        bail!("{} needs a file name", words[0]);
+    };
+    let mut description = FileDescription {
+        kind,
+        name: name.clone(),
+        line_number,
+        source_file: None,
+        record_contains: None,
+        block_contains: None,
+        recording_mode: None,
+        label_records: None,
+    };
+
+    let mut clauses = words[2..].iter().map(String::as_str).peekable();
+    while let Some(token) = clauses.next() {
+        match token.to_uppercase().as_str() {
+            "RECORD" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("contains") || t.eq_ignore_ascii_case("is"));
+                let mut record = RecordContains::default();
+                if clauses.next_if(|t| t.eq_ignore_ascii_case("varying")).is_some() {
+                    record.varying = true;
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("in"));
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("size"));
+                    if clauses.next_if(|t| t.eq_ignore_ascii_case("from")).is_some() {
+                        record.min = Some(file_integer(clauses.next(), "RECORD", name)?);
+                    }
+                    if clauses.next_if(|t| t.eq_ignore_ascii_case("to")).is_some() {
+                        record.max = Some(file_integer(clauses.next(), "RECORD", name)?);
+                    }
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("characters"));
+                    if clauses.next_if(|t| t.eq_ignore_ascii_case("depending")).is_some() {
+                        clauses.next_if(|t| t.eq_ignore_ascii_case("on"));
+                        record.depending_on = clauses.next().map(str::to_string);
+                    }
+                } else {
+                    let (min, max) = size_range(&mut clauses, "RECORD", name)?;
+                    clauses.next_if(|t| t.eq_ignore_ascii_case("characters"));
+                    record.min = min;
+                    record.max = Some(max);
+                }
+                description.record_contains = Some(record);
+            }
+            "BLOCK" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("contains"));
+                let (min, max) = size_range(&mut clauses, "BLOCK", name)?;
+                let unit = match clauses.next_if(|t| t.eq_ignore_ascii_case("records") || t.eq_ignore_ascii_case("characters")) {
+                    Some(unit) if unit.eq_ignore_ascii_case("records") => BlockUnit::Records,
+                    _ => BlockUnit::Characters,
+                };
+                description.block_contains = Some(BlockContains { min, max, unit });
+            }
+            "RECORDING" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("mode"));
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is"));
+                let Some(mode) = clauses.next() else {
+                    bail!("RECORDING MODE of {} needs a mode", name);
+                };
+                description.recording_mode = Some(mode.to_uppercase());
+            }
+            "LABEL" => {
+                clauses.next_if(|t| t.eq_ignore_ascii_case("record") || t.eq_ignore_ascii_case("records"));
+                clauses.next_if(|t| t.eq_ignore_ascii_case("is") || t.eq_ignore_ascii_case("are"));
+                match clauses.next() {
+                    Some(label) if label.eq_ignore_ascii_case("standard") || label.eq_ignore_ascii_case("omitted") => {
+                        description.label_records = Some(label.to_uppercase());
+                    }
+                    _ => bail!("LABEL RECORDS of {} needs STANDARD or OMITTED", name),
+                }
+            }
+            // Clauses that say nothing about the record area are passed over whole
+            "DATA" | "VALUE" | "LINAGE" | "CODE-SET" | "REPORT" | "REPORTS" => {
+                clauses.next_if(|t| token.eq_ignore_ascii_case("data") && t.to_uppercase().starts_with("RECORD"));
+                while clauses.next_if(|t| !is_file_clause(t)).is_some() {}
+            }
+            "IS" | "EXTERNAL" | "GLOBAL" => {}
+            _ => bail!("unrecognized clause {} in the entry for {}", token, name),
+        }
+    }
+
+    Ok(description)
+}
+
+/// Words that start a clause of an FD or SD entry.
+fn is_file_clause(word: &str) -> bool {
+    matches!(
+        word.to_uppercase().as_str(),
+        "RECORD" | "BLOCK" | "RECORDING" | "LABEL" | "DATA" | "VALUE" | "LINAGE" | "CODE-SET" | "REPORT"
+            | "REPORTS" | "EXTERNAL" | "GLOBAL"
+    )
+}
+
+/// Reads `n` or `n TO m` from a RECORD or BLOCK clause.
+fn size_range<'a>(
+    clauses: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+    clause: &str,
+    file: &str,
+) -> Result<(Option<usize>, usize)> {
+    let first = file_integer(clauses.next(), clause, file)?;
+    if clauses.next_if(|t| t.eq_ignore_ascii_case("to")).is_some() {
+        Ok((Some(first), file_integer(clauses.next(), clause, file)?))
+    } else {
+        Ok((None, first))
     }
-    
-    if Regex::new(r"occurs\s+\d+\s+to")?.is_match(&lower_content) {
-        bail!("ERROR: variable length files not implemented");
+}
+
+fn file_integer(word: Option<&str>, clause: &str, file: &str) -> Result<usize> {
+    match word.and_then(|w| w.parse().ok()) {
+        Some(n) => Ok(n),
+        None => bail!("{} of {} needs an integer", clause, file),
     }
-    
-    if lower_content.contains("redefines") {
-        bail!("ERROR: redefines not implemented");
+}
+
+/// Keeps the entries of a whole program's DATA DIVISION; input without a
+/// DATA DIVISION header is taken to be data descriptions throughout.
+fn data_division(entries: Vec<SourceEntry>) -> Vec<SourceEntry> {
+    let is_header = |entry: &SourceEntry, division: &str| {
+        let mut words = entry.text.trim_end_matches('.').split_whitespace();
+        words.next().is_some_and(|w| w.eq_ignore_ascii_case(division))
+            && words.next().is_some_and(|w| w.eq_ignore_ascii_case("division"))
+    };
+    let Some(start) = entries.iter().position(|e| is_header(e, "data")) else {
+        return entries;
+    };
+    let end = entries.iter().position(|e| is_header(e, "procedure")).unwrap_or(entries.len());
+    entries.into_iter().take(end).skip(start + 1).collect()
+}
+
+/// The name of the section an entry such as `WORKING-STORAGE SECTION.` begins.
+fn section_name(line: &str) -> Option<String> {
+    let words: Vec<&str> = line.trim_end_matches('.').split_whitespace().collect();
+    match words[..] {
+        [name, section] if section.eq_ignore_ascii_case("section") => Some(name.to_uppercase()),
+        _ => None,
     }
-    
-    Ok(())
 }
 
-fn parse_cobol_line(line: &str, line_number: usize) -> Result<Option<CobolField>> {
-    // Regex to match COBOL field definitions - case insensitive
-    // Pattern: level-number field-name [PIC picture-string] [COMP-3] [OCCURS n TIMES] [.]
-    let field_regex = Regex::new(
-        r"(?i)^\s*(\d{2})\s+([A-Za-z][\w-]*)\s*(?:pic\s+([^\s.]+))?\s*(?:(comp(?:-?[0-9]+)?))?\s*(?:occurs\s+(\d+)\s+times?)?\s*\.?"
-    )?;
-    
-    if let Some(captures) = field_regex.captures(line) {
-        let level: u32 = captures.get(1).unwrap().as_str().parse()?;
-        let name = captures.get(2).unwrap().as_str().to_string();
-        let picture = captures.get(3).map(|m| m.as_str().to_string());
-        let data_type = captures.get(4).and_then(|m| {
-            let s = m.as_str();
-            if s.is_empty() { None } else { Some(s.to_uppercase()) }
+/// Where a level number sits in the hierarchy: a 77 item stands alone like an 01 record.
+fn hierarchy_level(level: u32) -> u32 {
+    if level == 77 { 1 } else { level }
+}
+
+/// Parses a level-88 entry:
+///   88 condition-name VALUE[S] [IS|ARE] literal [THRU|THROUGH literal] [, literal ...]
+fn parse_condition_line(line: &str, line_number: usize) -> Result<Option<ConditionName>> {
+    let tokens = split_clauses(line);
+    if tokens.first().map(String::as_str) != Some("88") {
+        return Ok(None);
+    }
+    let Some(name) = tokens.get(1) else {
+        bail!("level 88 needs a condition name");
+    };
+
+    let mut words = tokens[2..].iter().map(String::as_str).peekable();
+    if !words.next().is_some_and(|w| w.eq_ignore_ascii_case("value") || w.eq_ignore_ascii_case("values")) {
+        bail!("condition {} needs a VALUE clause", name);
+    }
+    words.next_if(|w| w.eq_ignore_ascii_case("is") || w.eq_ignore_ascii_case("are"));
+
+    let mut values = Vec::new();
+    while let Some(word) = words.next() {
+        // WHEN SET TO FALSE names no values of the condition itself
+        if word.eq_ignore_ascii_case("when") || word.eq_ignore_ascii_case("false") {
+            break;
+        }
+        let thru = match words.next_if(|w| w.eq_ignore_ascii_case("thru") || w.eq_ignore_ascii_case("through")) {
+            Some(_) => match words.next() {
+                Some(end) => Some(end.to_string()),
+                None => bail!("THRU of condition {} needs a literal", name),
+            },
+            None => None,
+        };
+        values.push(ConditionValue {
+            from: word.to_string(),
+            thru,
         });
-        let occurs = captures.get(5).and_then(|m| m.as_str().parse().ok());
-
-        Ok(Some(CobolField {
-            level,
-            name,
-            picture,
-            data_type,
-            occurs,
-            children: Vec::new(),
-            line_number,
-        }))
-    } else {
-        Ok(None)
+    }
+    if values.is_empty() {
+        bail!("condition {} needs a VALUE clause", name);
+    }
+
+    Ok(Some(ConditionName {
+        name: name.clone(),
+        values,
+        line_number,
+    }))
+}
+
+/// Parses a level-66 entry:
+///   66 new-name RENAMES data-name-1 [THRU|THROUGH data-name-2]
+fn parse_renames_line(line: &str, line_number: usize) -> Result<Option<Renames>> {
+    let tokens = split_clauses(line);
+    if tokens.first().map(String::as_str) != Some("66") {
+        return Ok(None);
+    }
+    let Some(name) = tokens.get(1) else {
+        bail!("level 66 needs a name");
+    };
+
+    // Qualifiers such as `OF Cust-Record` only repeat the record being renamed
+    fn next_name<'a>(words: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> Option<String> {
+        let word = words.next();
+        while words.next_if(|w| w.eq_ignore_ascii_case("of") || w.eq_ignore_ascii_case("in")).is_some() {
+            words.next();
+        }
+        word.map(str::to_string)
+    }
+    let mut words = tokens[2..].iter().map(String::as_str).peekable();
+
+    if !words.next().is_some_and(|w| w.eq_ignore_ascii_case("renames")) {
+        bail!("level 66 item {} needs a RENAMES clause", name);
+    }
+    let Some(from) = next_name(&mut words) else {
+        bail!("RENAMES of {} needs a data name", name);
+    };
+    let thru = match words.next_if(|w| w.eq_ignore_ascii_case("thru") || w.eq_ignore_ascii_case("through")) {
+        Some(_) => match next_name(&mut words) {
+            Some(thru) => Some(thru),
+            None => bail!("RENAMES of {} needs a data name after THRU", name),
+        },
+        None => None,
+    };
+
+    Ok(Some(Renames {
+        name: name.clone(),
+        from,
+        thru,
+        line_number,
+    }))
+}
+
+/// Checks that a record with a REDEFINES clause names an earlier record of
+/// the same section.
+fn check_record_redefines(records: &[CobolField], diagnostics: &mut Diagnostics, main_file: Option<&str>) {
+    for (index, record) in records.iter().enumerate() {
+        let Some(target) = &record.redefines else {
+            continue;
+        };
+        let found = records[..index]
+            .iter()
+            .any(|earlier| earlier.section == record.section && earlier.name.eq_ignore_ascii_case(target));
+        if !found {
+            diagnostics.error(
+                record.source_file.as_deref().or(main_file),
+                record.line_number,
+                None,
+                format!("{} redefines {}, which is not a preceding record", record.name, target),
+            );
+        }
+    }
+}
+
+/// Checks that each RENAMES of `record` names items of the record that no
+/// OCCURS repeats, with the THRU item not declared before the first one.
+fn resolve_renames(record: &CobolField, diagnostics: &mut Diagnostics, main_file: Option<&str>) {
+    fn collect<'a>(field: &'a CobolField, repeated: bool, names: &mut Vec<(&'a str, bool)>) {
+        for child in &field.children {
+            let repeated = repeated || child.occurs.is_some();
+            names.push((&child.name, repeated));
+            collect(child, repeated, names);
+        }
+    }
+    let mut names = Vec::new();
+    collect(record, false, &mut names);
+    let index_of = |target: &str| names.iter().position(|(n, _)| n.eq_ignore_ascii_case(target));
+    let file = record.source_file.as_deref().or(main_file);
+
+    for renames in &record.renames {
+        let last = renames.thru.as_deref().unwrap_or(&renames.from);
+        for target in [&renames.from, last] {
+            match index_of(target) {
+                None => diagnostics.error(
+                    file,
+                    renames.line_number,
+                    None,
+                    format!("{} renames {}, which is not an item of {}", renames.name, target, record.name),
+                ),
+                Some(index) if names[index].1 => diagnostics.error(
+                    file,
+                    renames.line_number,
+                    None,
+                    format!("{} renames {}, which is repeated by an OCCURS clause", renames.name, target),
+                ),
+                Some(_) => {}
+            }
+        }
+        if let (Some(from), Some(thru)) = (index_of(&renames.from), index_of(last)) {
+            if thru < from {
+                diagnostics.error(
+                    file,
+                    renames.line_number,
+                    None,
+                    format!("{} renames {} through {}, which comes before it", renames.name, renames.from, last),
+                );
+            }
+        }
+    }
+}
+
+/// Reports level numbers outside 01-49, 77 and 88, items whose level differs
+/// from the siblings before them, and items that are elementary but have
+/// subordinate items or are groups yet have a PICTURE.
+fn check_hierarchy(field: &CobolField, diagnostics: &mut Diagnostics, main_file: Option<&str>) {
+    let file = field.source_file.as_deref().or(main_file);
+    if !matches!(field.level, 1..=49 | 77) {
+        diagnostics.error(
+            file,
+            field.line_number,
+            None,
+            format!("level number {:02} of {} is not valid; use 01-49, 66, 77 or 88", field.level, field.name),
+        );
+    }
+
+    if field.children.is_empty() {
+        // Other usages are checked against the PICTURE by check_usage
+        let display = field.data_type.as_deref().is_none_or(|usage| matches!(usage, "DISPLAY" | "NATIONAL"));
+        if field.picture.is_none() && display {
+            diagnostics.error(
+                file,
+                field.line_number,
+                None,
+                format!("{} has neither a PICTURE nor subordinate items", field.name),
+            );
+        }
+        return;
+    }
+
+    if field.picture.is_some() {
+        diagnostics.error(
+            file,
+            field.line_number,
+            None,
+            format!("{} has subordinate items, so it is a group and cannot have a PICTURE", field.name),
+        );
+    } else if field.level == 77 || matches!(field.data_type.as_deref(), Some("COMP-1" | "COMP-2" | "POINTER" | "INDEX")) {
+        let reason = if field.level == 77 {
+            "level 77".to_string()
+        } else {
+            format!("USAGE {}", field.data_type.as_deref().unwrap_or_default())
+        };
+        diagnostics.error(
+            file,
+            field.line_number,
+            None,
+            format!("{} is an elementary item ({}) but has subordinate items", field.name, reason),
+        );
+    }
+
+    let sibling_level = field.children[0].level;
+    for child in &field.children {
+        if child.level != sibling_level {
+            diagnostics.error(
+                child.source_file.as_deref().or(main_file),
+                child.line_number,
+                None,
+                format!(
+                    "{} is level {:02}, but the items before it under {} are level {:02}",
+                    child.name, child.level, field.name, sibling_level
+                ),
+            );
+        }
+        check_hierarchy(child, diagnostics, main_file);
+    }
+}
+
+/// Reports REDEFINES whose subject or object holds an OCCURS DEPENDING ON
+/// table, which the standard forbids: the alternatives would then have no
+/// one length to share.
+fn check_redefined_tables(field: &CobolField, diagnostics: &mut Diagnostics, main_file: Option<&str>) {
+    fn contains_depending_on(field: &CobolField) -> bool {
+        field.depending_on.is_some() || field.children.iter().any(contains_depending_on)
+    }
+
+    for (index, child) in field.children.iter().enumerate() {
+        if let Some(target) = &child.redefines {
+            let object = field.children[..index].iter().rev().find(|c| c.name.eq_ignore_ascii_case(target));
+            let variable = [Some(child), object].into_iter().flatten().find(|f| contains_depending_on(f));
+            if let Some(variable) = variable {
+                diagnostics.error(
+                    child.source_file.as_deref().or(main_file),
+                    child.line_number,
+                    None,
+                    format!(
+                        "{} redefines {}, but {} holds an OCCURS DEPENDING ON table",
+                        child.name, target, variable.name
+                    ),
+                );
+            }
+        }
+        check_redefined_tables(child, diagnostics, main_file);
+    }
+}
+
+/// Reports elementary items whose USAGE cannot hold their PICTURE, which
+/// would leave their storage wrong.
+fn check_usage(field: &CobolField, diagnostics: &mut Diagnostics, main_file: Option<&str>) {
+    for child in &field.children {
+        check_usage(child, diagnostics, main_file);
+    }
+    if !field.children.is_empty() {
+        return;
+    }
+
+    let file = field.source_file.as_deref().or(main_file);
+    let mut report = |message: String| diagnostics.error(file, field.line_number, None, message);
+    let usage = field.data_type.as_deref().unwrap_or("DISPLAY");
+    let picture = match field.picture.as_deref().map(|p| (p, parse_picture(p))) {
+        Some((_, Err(err))) => return report(format!("{}: {}", field.name, err)),
+        Some((text, Ok(picture))) => Some((text, picture)),
+        None => None,
+    };
+
+    match (usage, picture) {
+        ("COMP-1" | "COMP-2" | "POINTER" | "INDEX", Some(_)) => {
+            report(format!("{} is USAGE {}, which takes no PICTURE", field.name, usage))
+        }
+        ("COMP" | "COMP-3" | "COMP-4" | "COMP-5" | "COMP-X" | "BINARY" | "PACKED-DECIMAL", None) => {
+            report(format!("{} is USAGE {} but has no PICTURE", field.name, usage))
+        }
+        ("COMP" | "COMP-3" | "COMP-4" | "COMP-5" | "BINARY" | "PACKED-DECIMAL", Some((text, picture)))
+            if picture.category != PictureCategory::Numeric =>
+        {
+            report(format!("{} is USAGE {}, which needs a numeric PICTURE, not {}", field.name, usage, text))
+        }
+        _ => {}
+    }
+
+    if field.sign.is_some() && !is_signed_display(field) {
+        report(format!("{} has a SIGN clause, which needs a signed numeric DISPLAY item", field.name));
+    }
+}
+
+/// Takes the run of data names a KEY or INDEXED BY phrase lists, stopping at
+/// the next clause.
+fn table_names<'a>(clauses: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> Vec<String> {
+    let mut names = Vec::new();
+    while let Some(name) = clauses.next_if(|t| {
+        let is_name = t.starts_with(|c: char| c.is_ascii_alphabetic())
+            && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let is_phrase = matches!(
+            t.to_uppercase().as_str(),
+            "ASCENDING" | "DESCENDING" | "INDEXED" | "DEPENDING" | "KEY" | "LEADING" | "TRAILING"
+        );
+        is_name && !is_phrase && !is_clause_keyword(t)
+    }) {
+        names.push(name.to_string());
+    }
+    names
+}
+
+/// Splits an entry into words, keeping quoted literals whole and dropping the
+/// terminating period, the comma and semicolon separators and any `*>` inline
+/// comment.
+fn split_clauses(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' => {
+                current.push(c);
+                quote = Some(c);
+            }
+            None if c == '*' && chars.peek() == Some(&'>') => break,
+            None if c.is_whitespace() => push_word(&mut tokens, std::mem::take(&mut current)),
+            None => current.push(c),
+        }
+    }
+    push_word(&mut tokens, current);
+
+    if let Some(last) = tokens.last_mut() {
+        if last == "." {
+            tokens.pop();
+        } else if last.ends_with('.') && quote.is_none() {
+            last.pop();
+        }
+    }
+
+    tokens
+}
+
+/// Adds a word to `tokens` without the comma or semicolon separator that may
+/// follow it; a separator standing alone is dropped. Inside a literal these
+/// are part of it, and the closing quote keeps them from being trimmed.
+fn push_word(tokens: &mut Vec<String>, word: String) {
+    let word = word.trim_end_matches([',', ';']);
+    if !word.is_empty() {
+        tokens.push(word.to_string());
+    }
+}
+
+/// Words that start a clause, so an entry beginning with one has no name.
+fn is_clause_keyword(word: &str) -> bool {
+    matches!(
+        word.to_uppercase().as_str(),
+        "REDEFINES" | "PIC" | "PICTURE" | "USAGE" | "OCCURS" | "VALUE" | "VALUES" | "SIGN" | "SYNC"
+            | "SYNCHRONIZED" | "JUST" | "JUSTIFIED" | "BLANK" | "EXTERNAL" | "GLOBAL" | "IS"
+    ) || is_usage(word)
+}
+
+fn is_usage(word: &str) -> bool {
+    matches!(
+        normalize_usage(word).as_str(),
+        "COMP" | "COMP-1" | "COMP-2" | "COMP-3" | "COMP-4" | "COMP-5" | "COMP-X"
+            | "BINARY" | "PACKED-DECIMAL" | "DISPLAY" | "NATIONAL" | "POINTER" | "INDEX"
+    )
+}
+
+/// Gives elementary items the USAGE declared on an enclosing group, unless
+/// they declare their own.
+fn inherit_usage(field: &mut CobolField, inherited: Option<&str>) {
+    if field.children.is_empty() {
+        if field.data_type.is_none() {
+            field.data_type = inherited.map(str::to_string);
+        }
+        return;
+    }
+
+    let usage = field.data_type.clone().or(inherited.map(str::to_string));
+    for child in &mut field.children {
+        inherit_usage(child, usage.as_deref());
+    }
+}
+
+/// Applies a SIGN clause written on a group to the signed numeric DISPLAY
+/// items under it that do not give their own.
+fn inherit_sign(field: &mut CobolField, inherited: Option<SignClause>) {
+    if field.children.is_empty() {
+        if field.sign.is_none() && is_signed_display(field) {
+            field.sign = inherited;
+        }
+        return;
+    }
+
+    let sign = field.sign.or(inherited);
+    for child in &mut field.children {
+        inherit_sign(child, sign);
+    }
+}
+
+/// Whether an elementary item is signed numeric DISPLAY, the only kind of
+/// item a SIGN clause can describe.
+fn is_signed_display(field: &CobolField) -> bool {
+    let display = field.data_type.as_deref().is_none_or(|usage| usage == "DISPLAY");
+    let signed = field.picture.as_deref().is_some_and(|picture| {
+        parse_picture(picture).is_ok_and(|p| p.signed && p.category == PictureCategory::Numeric)
+    });
+    display && signed
+}
+
+/// Spells a usage the way the layout shows it: `computational-3` and `comp3`
+/// both become `COMP-3`.
+fn normalize_usage(usage: &str) -> String {
+    let usage = usage.to_uppercase().replace("COMPUTATIONAL", "COMP");
+    match usage.strip_prefix("COMP") {
+        Some(suffix) if !suffix.is_empty() && !suffix.starts_with('-') => format!("COMP-{}", suffix),
+        _ => usage,
     }
 }
 
@@ -152,11 +1152,652 @@ mod tests {
     }
 
     #[test]
-    fn test_unsupported_features() {
-        let content = "01 test redefines something.";
-        assert!(check_unsupported_features(content).is_err());
-        
-        let content2 = "01 test varying in size.";
-        assert!(check_unsupported_features(content2).is_err());
+    fn test_parse_binary_usages() {
+        let field = parse_cobol_line("05  Ws-Count pic s9(4) computational-5.", 1).unwrap().unwrap();
+        assert_eq!(field.data_type, Some("COMP-5".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Flags pic 9(2) comp-x.", 1).unwrap().unwrap();
+        assert_eq!(field.data_type, Some("COMP-X".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Total pic s9(7)v99 packed-decimal.", 1).unwrap().unwrap();
+        assert_eq!(field.data_type, Some("PACKED-DECIMAL".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Ptr pointer.", 1).unwrap().unwrap();
+        assert_eq!(field.picture, None);
+        assert_eq!(field.data_type, Some("POINTER".to_string()));
+    }
+
+    #[test]
+    fn test_parse_usage_clause_any_order() {
+        let field = parse_cobol_line("05  Ws-Amt usage is packed-decimal pic s9(7)v99.", 1).unwrap().unwrap();
+        assert_eq!(field.picture, Some("s9(7)v99".to_string()));
+        assert_eq!(field.data_type, Some("PACKED-DECIMAL".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Cnt pic 9(4) usage binary occurs 3.", 1).unwrap().unwrap();
+        assert_eq!(field.data_type, Some("BINARY".to_string()));
+        assert_eq!(field.occurs, Some(3));
+
+        let field = parse_cobol_line("05  Ws-Txt picture is x(5) display.", 1).unwrap().unwrap();
+        assert_eq!(field.picture, Some("x(5)".to_string()));
+        assert_eq!(field.data_type, Some("DISPLAY".to_string()));
+    }
+
+    #[test]
+    fn test_group_usage_is_inherited() {
+        let content = "       01  Ws-Totals usage comp-3.
+           02  Ws-Day          pic s9(7)v99.
+           02  Ws-Month        pic s9(9)v99.
+           02  Ws-Counts usage binary.
+               03  Ws-Lines    pic 9(4).
+               03  Ws-Pages    pic 9(4) display.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let totals = &structure.root_fields[0];
+
+        assert_eq!(totals.children[0].data_type, Some("COMP-3".to_string()));
+        assert_eq!(totals.children[1].data_type, Some("COMP-3".to_string()));
+        assert_eq!(totals.children[2].children[0].data_type, Some("BINARY".to_string()));
+        assert_eq!(totals.children[2].children[1].data_type, Some("DISPLAY".to_string()));
+    }
+
+    #[test]
+    fn test_sequence_numbers_are_ignored() {
+        let content = "\
+000100 01  Cust-Record.                                                 CUSTREC
+000200*    02  Cust-Old             pic x(4).
+000300     02  Cust-Id              pic x(6).                           CUSTREC
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let record = &structure.root_fields[0];
+
+        assert_eq!(record.name, "Cust-Record");
+        assert_eq!(record.children.len(), 1);
+        assert_eq!(record.children[0].picture, Some("x(6)".to_string()));
+        assert_eq!(record.children[0].line_number, 3);
+    }
+
+    #[test]
+    fn test_multi_line_entry() {
+        let content = "       01  Ws-Record.
+           05  Ws-Key                 pic x(4).
+           05  Ws-Amount
+               PIC S9(7)V99 COMP-3.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let amount = &structure.root_fields[0].children[1];
+
+        assert_eq!(amount.name, "Ws-Amount");
+        assert_eq!(amount.picture, Some("S9(7)V99".to_string()));
+        assert_eq!(amount.data_type, Some("COMP-3".to_string()));
+        assert_eq!(amount.line_number, 3);
+    }
+
+    #[test]
+    fn test_copybook_provenance() {
+        let dir = std::env::temp_dir().join(format!("mkrl-parser-copy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("CUSTREC.cpy"), "      * customer fields\n           05  :PFX:-Id pic x(6).\n").unwrap();
+
+        let options = ParseOptions {
+            include_dirs: vec![dir.clone()],
+            ..Default::default()
+        };
+        let content = "       01  Cust-Record.
+           COPY CUSTREC REPLACING ==:PFX:== BY ==Cm==.
+           05  Cust-Tail pic x.
+";
+        let structure = parse_cobol_structure(content, &options).unwrap();
+        let record = &structure.root_fields[0];
+
+        assert_eq!(record.children[0].name, "Cm-Id");
+        assert_eq!(record.children[0].line_number, 2);
+        assert!(record.children[0].source_file.as_deref().unwrap().ends_with("CUSTREC.cpy"));
+        assert_eq!(record.children[1].source_file, None);
+        assert_eq!(record.children[1].line_number, 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_unnamed_items() {
+        let field = parse_cobol_line("02                      pic x(180).", 1).unwrap().unwrap();
+        assert_eq!(field.name, "FILLER");
+        assert!(field.is_filler());
+        assert_eq!(field.picture, Some("x(180)".to_string()));
+
+        let field = parse_cobol_line("01  redefines File-Status.", 1).unwrap().unwrap();
+        assert!(field.is_filler());
+        assert_eq!(field.redefines, Some("File-Status".to_string()));
+
+        let field = parse_cobol_line("05  filler pic x(10).", 1).unwrap().unwrap();
+        assert_eq!(field.name, "FILLER");
+
+        let field = parse_cobol_line("02  pic x occurs 0 to 255 times depending on Carlock-Rec-Len.", 1).unwrap().unwrap();
+        assert!(field.is_filler());
+        assert_eq!(field.occurs, Some(255));
+    }
+
+    #[test]
+    fn test_parse_edited_picture() {
+        let line = "           02  Pr-Amount pic zz,zz9.99.";
+        let field = parse_cobol_line(line, 1).unwrap().unwrap();
+
+        assert_eq!(field.picture, Some("zz,zz9.99".to_string()));
+    }
+
+    #[test]
+    fn test_parse_redefines_field() {
+        let line = "           02  Bd-Ref-X redefines Bd-Ref pic x(4).";
+        let field = parse_cobol_line(line, 1).unwrap().unwrap();
+
+        assert_eq!(field.name, "Bd-Ref-X");
+        assert_eq!(field.redefines, Some("Bd-Ref".to_string()));
+        assert_eq!(field.picture, Some("x(4)".to_string()));
+    }
+
+    #[test]
+    fn test_parse_occurs_depending_on() {
+        let line = "           02  Cl-Byte pic x occurs 0 to 255 times depending on Cl-Len.";
+        let field = parse_cobol_line(line, 1).unwrap().unwrap();
+
+        assert_eq!(field.occurs, Some(255));
+        assert_eq!(field.occurs_min, Some(0));
+        assert_eq!(field.depending_on, Some("Cl-Len".to_string()));
+    }
+
+    #[test]
+    fn test_redefined_tables_cannot_vary() {
+        let every = "       01  Rec.
+           05  Rec-Count       pic 99.
+           05  Rec-Short.
+               10  Rec-Code pic x occurs 1 to 50
+                       depending on Rec-Count.
+           05  Rec-Long redefines Rec-Short.
+               10  Rec-Name pic xx occurs 1 to 50
+                       depending on Rec-Count.
+";
+        let err = parse_cobol_structure(every, &ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "6: error: Rec-Long redefines Rec-Short, but Rec-Long holds an OCCURS DEPENDING ON table\n1 error, 0 warnings"
+        );
+
+        let shorter = "       01  Rec.
+           05  Rec-Count       pic 99.
+           05  Rec-Fixed       pic x(100).
+           05  Rec-Table redefines Rec-Fixed.
+               10  Rec-Code pic x occurs 1 to 90
+                       depending on Rec-Count.
+           05  Rec-Tail        pic x.
+";
+        let err = parse_cobol_structure(shorter, &ParseOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("Rec-Table redefines Rec-Fixed, but Rec-Table holds"));
+    }
+
+    #[test]
+    fn test_condition_names_attach_to_owner() {
+        let content = "       01  Cust-Record.
+           05  Cust-Status     pic x.
+               88  Cust-Active         value 'A'.
+               88  Cust-Closed         values are 'C' 'X' thru 'Z'.
+           05  Cust-Score      pic 9(3).
+               88  Cust-Good   value 700 through 850, 900
+                                       when set to false 0.
+           05  Cust-Tail       pic x.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let record = &structure.root_fields[0];
+
+        assert_eq!(record.children.len(), 3);
+        let status = &record.children[0];
+        assert!(status.children.is_empty());
+        assert_eq!(status.conditions.len(), 2);
+        assert_eq!(status.conditions[0].name, "Cust-Active");
+        assert_eq!(status.conditions[0].line_number, 3);
+        assert_eq!(
+            status.conditions[1].values,
+            vec![
+                ConditionValue { from: "'C'".to_string(), thru: None },
+                ConditionValue { from: "'X'".to_string(), thru: Some("'Z'".to_string()) },
+            ]
+        );
+        assert_eq!(
+            record.children[1].conditions[0].values,
+            vec![
+                ConditionValue { from: "700".to_string(), thru: Some("850".to_string()) },
+                ConditionValue { from: "900".to_string(), thru: None },
+            ]
+        );
+        assert_eq!(record.children[2].name, "Cust-Tail");
+    }
+
+    #[test]
+    fn test_condition_values_continue_on_numbered_lines() {
+        let content = "       01  Rec.
+           05  Rec-Code        pic 99.
+               88  Rec-Listed  values 1 2 3
+                               10 11 12.
+               88  Rec-Ranged  values 1 thru 5
+                               10 thru 12.
+           05  Rec-Tail        pic x.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let record = &structure.root_fields[0];
+        let conditions = &record.children[0].conditions;
+        let values = |index: usize| -> Vec<(&str, Option<&str>)> {
+            conditions[index].values.iter().map(|v| (v.from.as_str(), v.thru.as_deref())).collect()
+        };
+
+        assert_eq!(
+            values(0),
+            vec![("1", None), ("2", None), ("3", None), ("10", None), ("11", None), ("12", None)]
+        );
+        assert_eq!(values(1), vec![("1", Some("5")), ("10", Some("12"))]);
+        assert_eq!(record.children[1].name, "Rec-Tail");
+        assert!(structure.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_condition_needs_values_and_owner() {
+        assert!(parse_condition_line("88  Cust-Active.", 1).is_err());
+        assert!(parse_condition_line("88  Cust-Range value 1 thru.", 1).is_err());
+        assert!(parse_condition_line("05  Cust-Status pic x.", 1).unwrap().is_none());
+        assert!(parse_cobol_structure("       88  Orphan value 'Y'.\n", &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_renames_belong_to_record() {
+        let content = "       01  Cust-Record.
+           05  Cust-Id         pic x(6).
+           05  Cust-Name.
+               10  Cust-First  pic x(10).
+               10  Cust-Last   pic x(15).
+           05  Cust-Zip        pic 9(5).
+       66  Cust-Key renames Cust-Id thru Cust-First.
+       66  Cust-Postal renames Cust-Zip of Cust-Record.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let record = &structure.root_fields[0];
+
+        assert_eq!(structure.root_fields.len(), 1);
+        assert_eq!(record.children.len(), 3);
+        assert_eq!(record.renames.len(), 2);
+        assert_eq!(record.renames[0].name, "Cust-Key");
+        assert_eq!(record.renames[0].from, "Cust-Id");
+        assert_eq!(record.renames[0].thru, Some("Cust-First".to_string()));
+        assert_eq!(record.renames[1].from, "Cust-Zip");
+        assert_eq!(record.renames[1].thru, None);
+    }
+
+    #[test]
+    fn test_renames_must_resolve() {
+        let record = "       01  Rec.
+           05  Rec-A           pic x.
+           05  Rec-B           pic x.
+";
+        let unknown = format!("{}       66  Rec-Alias renames Rec-A thru Rec-Z.\n", record);
+        let err = parse_cobol_structure(&unknown, &ParseOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("not an item of Rec"));
+
+        let backwards = format!("{}       66  Rec-Alias renames Rec-B thru Rec-A.\n", record);
+        assert!(parse_cobol_structure(&backwards, &ParseOptions::default()).is_err());
+
+        assert!(parse_renames_line("66  Rec-Alias Rec-A.", 1).is_err());
+
+        let table = "       01  Rec.
+           05  Rec-A           pic x.
+           05  Rec-Rows occurs 3 times.
+               10  Rec-Code    pic x.
+               10  Rec-Amount  pic 9(4).
+       66  Rec-Alias renames Rec-A thru Rec-Code.
+";
+        let err = parse_cobol_structure(table, &ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "6: error: Rec-Alias renames Rec-Code, which is repeated by an OCCURS clause\n1 error, 0 warnings"
+        );
+    }
+
+    #[test]
+    fn test_level_77_and_several_records() {
+        let content = "       fd  Tran-File.
+       01  Tran-Header.
+           05  Th-Type         pic x.
+           05  Th-Date         pic 9(8).
+       01  Tran-Detail.
+           05  Td-Type         pic x.
+       77  Ws-Count            pic 9(4) comp.
+       77  Ws-Flag             pic x.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let names: Vec<&str> = structure.root_fields.iter().map(|f| f.name.as_str()).collect();
+
+        assert_eq!(structure.files[0].name, "Tran-File");
+        assert_eq!(structure.root_fields[1].file_description, Some("Tran-File".to_string()));
+        assert_eq!(names, vec!["Tran-Header", "Tran-Detail", "Ws-Count", "Ws-Flag"]);
+        assert_eq!(structure.root_fields[1].children.len(), 1);
+        assert_eq!(structure.root_fields[2].level, 77);
+    }
+
+    #[test]
+    fn test_parse_sign_clause() {
+        let field = parse_cobol_line("05  Ws-Amt pic s9(5) sign is leading separate character.", 1).unwrap().unwrap();
+        assert_eq!(field.sign, Some(SignClause { position: SignPosition::Leading, separate: true }));
+
+        let field = parse_cobol_line("05  Ws-Amt pic s9(5) trailing.", 1).unwrap().unwrap();
+        assert_eq!(field.sign, Some(SignClause { position: SignPosition::Trailing, separate: false }));
+
+        assert!(parse_cobol_line("05  Ws-Amt pic s9(5) sign separate.", 1).is_err());
+    }
+
+    #[test]
+    fn test_group_sign_applies_to_signed_items() {
+        let content = "       01  Ws-Totals sign trailing separate.
+           05  Ws-Day          pic s9(5).
+           05  Ws-Code         pic x(3).
+           05  Ws-Month        pic s9(7) sign leading.
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let totals = &structure.root_fields[0];
+
+        assert_eq!(totals.children[0].sign, Some(SignClause { position: SignPosition::Trailing, separate: true }));
+        assert_eq!(totals.children[1].sign, None);
+        assert_eq!(totals.children[2].sign, Some(SignClause { position: SignPosition::Leading, separate: false }));
+    }
+
+    #[test]
+    fn test_sign_needs_signed_display() {
+        let content = "       01  Rec sign leading separate.
+           05  Rec-Packed      pic s9(5) comp-3.
+           05  Rec-Plain       pic 9(5) sign leading separate.
+           05  Rec-Binary      pic s9(5) comp sign trailing.
+           05  Rec-Name        pic x(5) sign leading.
+";
+        let options = ParseOptions {
+            source_path: Some(PathBuf::from("REC.FD")),
+            ..Default::default()
+        };
+        let err = parse_cobol_structure(content, &options).unwrap_err();
+
+        // A group's SIGN passes over the items it cannot describe
+        assert_eq!(
+            err.to_string(),
+            "REC.FD:3: error: Rec-Plain has a SIGN clause, which needs a signed numeric DISPLAY item
+REC.FD:4: error: Rec-Binary has a SIGN clause, which needs a signed numeric DISPLAY item
+REC.FD:5: error: Rec-Name has a SIGN clause, which needs a signed numeric DISPLAY item
+3 errors, 0 warnings"
+        );
+    }
+
+    #[test]
+    fn test_parse_synchronized() {
+        let field = parse_cobol_line("05  Ws-Len pic s9(4) comp sync.", 1).unwrap().unwrap();
+        assert_eq!(field.synchronized, Some(Synchronized::Plain));
+        assert_eq!(field.data_type, Some("COMP".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Len synchronized right pic s9(9) binary.", 1).unwrap().unwrap();
+        assert_eq!(field.synchronized, Some(Synchronized::Right));
+        assert_eq!(field.picture, Some("s9(9)".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Len pic s9(9) binary sync left.", 1).unwrap().unwrap();
+        assert_eq!(field.synchronized, Some(Synchronized::Left));
+    }
+
+    #[test]
+    fn test_parse_remaining_clauses() {
+        let field = parse_cobol_line("05  Ws-Total pic s9(7)v99 value zeros comp-3.", 1).unwrap().unwrap();
+        assert_eq!(field.value, Some("zeros".to_string()));
+        assert_eq!(field.data_type, Some("COMP-3".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Name value is 'N/A' justified right pic x(10).", 1).unwrap().unwrap();
+        assert_eq!(field.value, Some("'N/A'".to_string()));
+        assert!(field.justified);
+        assert_eq!(field.picture, Some("x(10)".to_string()));
+
+        let field = parse_cobol_line("05  Ws-Amt pic zz9.99 blank when zero value all '*'.", 1).unwrap().unwrap();
+        assert!(field.blank_when_zero);
+        assert_eq!(field.value, Some("all '*'".to_string()));
+
+        let field = parse_cobol_line("01  Ws-Shared is external is global pic x(4) value 'AB' & 'CD'.", 1).unwrap().unwrap();
+        assert!(field.external && field.global);
+        assert_eq!(field.value, Some("'AB' & 'CD'".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_separators_are_ignored() {
+        let field = parse_cobol_line("05  A pic X(5), value spaces.", 1).unwrap().unwrap();
+        assert_eq!(field.picture, Some("X(5)".to_string()));
+        assert_eq!(field.value, Some("spaces".to_string()));
+
+        let field = parse_cobol_line("05  B ; pic zz,zz9; value 'A, B;' , blank when zero.", 1).unwrap().unwrap();
+        assert_eq!(field.picture, Some("zz,zz9".to_string()));
+        assert_eq!(field.value, Some("'A, B;'".to_string()));
+        assert!(field.blank_when_zero);
+
+        let field = parse_cobol_line("05  C pic s9(5), usage comp-3, sync.", 1).unwrap().unwrap();
+        assert_eq!(field.data_type, Some("COMP-3".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_clause_is_an_error() {
+        let err = parse_cobol_line("05  Ws-Amt pic 9(4) frobnicate comp.", 7).unwrap_err();
+        assert_eq!(err.to_string(), "unrecognized clause frobnicate in the entry for Ws-Amt");
+        assert!(parse_cobol_line("05  Ws-Amt pic 9(4) blank when.", 1).is_err());
+        assert!(parse_cobol_line("05  Ws-Amt pic 9(4) value.", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_table_keys_and_indexes() {
+        let field = parse_cobol_line(
+            "05  Ws-Rate occurs 12 times ascending key is Ws-Code Ws-Date descending Ws-Seq indexed by Ws-Idx Ws-Idx2.",
+            1,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(field.occurs, Some(12));
+        assert_eq!(
+            field.keys,
+            vec![
+                TableKey { ascending: true, name: "Ws-Code".to_string() },
+                TableKey { ascending: true, name: "Ws-Date".to_string() },
+                TableKey { ascending: false, name: "Ws-Seq".to_string() },
+            ]
+        );
+        assert_eq!(field.indexed_by, vec!["Ws-Idx".to_string(), "Ws-Idx2".to_string()]);
+
+        let field = parse_cobol_line("05  Ws-Row occurs 5 indexed Ws-X pic x(4) comp-x.", 1).unwrap().unwrap();
+        assert_eq!(field.indexed_by, vec!["Ws-X".to_string()]);
+        assert_eq!(field.picture, Some("x(4)".to_string()));
+        assert_eq!(field.data_type, Some("COMP-X".to_string()));
+
+        assert!(parse_cobol_line("05  Ws-Row occurs 5 indexed by.", 1).is_err());
+    }
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let content = "       01  Rec.
+           05  Rec-Id          pic x(4) frobnicate.
+           5A  Rec-Bad         pic x.
+           05  Rec-Amt         pic x(5) comp-3.
+           05  Rec-Rate        pic 9(3) comp-2.
+           05  Rec-Pk          comp-3.
+           05  Rec-Ok          pic x.
+";
+        let options = ParseOptions {
+            source_path: Some(PathBuf::from("REC.FD")),
+            ..Default::default()
+        };
+        let err = parse_cobol_structure(content, &options).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "REC.FD:2:12: error: unrecognized clause frobnicate in the entry for Rec-Id
+REC.FD:3:12: error: bad level number 5A
+REC.FD:4: error: Rec-Amt is USAGE COMP-3, which needs a numeric PICTURE, not x(5)
+REC.FD:5: error: Rec-Rate is USAGE COMP-2, which takes no PICTURE
+REC.FD:6: error: Rec-Pk is USAGE COMP-3 but has no PICTURE
+5 errors, 0 warnings"
+        );
+    }
+
+    #[test]
+    fn test_warnings_fail_only_when_strict() {
+        let content = "       01  Rec.
+           05  Rec-Id          pic x(4)
+           05  Rec-Tail        pic x.
+      x    05  Rec-Note        pic x.
+       SKIP1.
+      $SET NOTRUNC
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let warnings: Vec<String> = structure.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "2:12: warning: missing period at the end of the entry",
+                "4:7: warning: 'x' in column 7 is not an indicator",
+                "5:8: warning: unrecognized entry: SKIP1.",
+            ]
+        );
+        assert_eq!(structure.root_fields[0].children.len(), 3);
+
+        let strict = ParseOptions { strict: true, ..Default::default() };
+        let err = parse_cobol_structure(content, &strict).unwrap_err();
+        assert!(err.to_string().ends_with("0 errors, 3 warnings"));
+    }
+
+    #[test]
+    fn test_level_hierarchy_is_validated() {
+        let content = "       01  Rec.
+           02  Rec-Group.
+               05  Rec-A       pic x.
+               05  Rec-B       pic x.
+               03  Rec-C       pic x.
+           02  Rec-Odd.
+               50  Rec-D       pic x.
+           02  Rec-Pic         pic x(4).
+               03  Rec-E       pic x.
+           02  Rec-Empty.
+       77  Ws-Group.
+           05  Ws-F            pic x.
+";
+        let err = parse_cobol_structure(content, &ParseOptions::default()).unwrap_err().to_string();
+        let messages: Vec<&str> = err.lines().collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "5: error: Rec-C is level 03, but the items before it under Rec-Group are level 05",
+                "7: error: level number 50 of Rec-D is not valid; use 01-49, 66, 77 or 88",
+                "8: error: Rec-Pic has subordinate items, so it is a group and cannot have a PICTURE",
+                "10: error: Rec-Empty has neither a PICTURE nor subordinate items",
+                "11: error: Ws-Group is an elementary item (level 77) but has subordinate items",
+                "5 errors, 0 warnings",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_whole_program_records() {
+        let content = "       identification division.
+       program-id.  RL.
+       environment division.
+       input-output section.
+       file-control.
+           select Rl-File assign to disk Rl-File-Spec.
+       data division.
+       file section.
+       fd  Rl-File.
+       01  Rl-File-Record.
+           02             pic x(81).
+       sd  Sort-File.
+       01  Sort-Rec        pic x(10).
+       working-storage section.
+       01  Pgm-Id          pic x(15).
+       01  File-Status     pic x(2) value \"00\".
+       01  redefines File-Status.
+           02 Fs1          pic x(2).
+       screen section.
+       01  Main-Screen.
+           05  line 1 col 1 value \"Title\".
+       linkage section.
+       01  Ls-Parm         pic x(8).
+       procedure division using Ls-Parm.
+           move spaces to Pgm-Id.
+           copy \"NOT-THERE.CBL\".
+";
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let records: Vec<(&str, Option<DataSection>, Option<&str>)> = structure
+            .root_fields
+            .iter()
+            .map(|r| (r.name.as_str(), r.section, r.file_description.as_deref()))
+            .collect();
+
+        assert_eq!(
+            records,
+            vec![
+                ("Rl-File-Record", Some(DataSection::File), Some("Rl-File")),
+                ("Sort-Rec", Some(DataSection::File), Some("Sort-File")),
+                ("Pgm-Id", Some(DataSection::WorkingStorage), None),
+                ("File-Status", Some(DataSection::WorkingStorage), None),
+                ("FILLER", Some(DataSection::WorkingStorage), None),
+                ("Ls-Parm", Some(DataSection::Linkage), None),
+            ]
+        );
+        assert_eq!(structure.files[1].kind, FileKind::Sort);
+        let warnings: Vec<String> = structure.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(warnings, vec!["19:8: warning: SCREEN SECTION is not laid out"]);
+    }
+
+    #[test]
+    fn test_file_description_clauses() {
+        let content = "       fd  Cust-File is external
+           block contains 10 records
+           record contains 80 characters
+           recording mode is f
+           label records are standard
+           value of file-id is \"CUST.DAT\"
+           data record is Cust-Rec.
+       01  Cust-Rec            pic x(80).
+       fd  Carlock-File
+           record is varying in size from 0 to 255 characters
+           depending on Carlock-Rec-Len.
+       01  Carlock-Rec         pic x(255).
+       sd  Sort-File record 20 to 40.
+       01  Sort-Rec            pic x(40).
+       fd  Bad-File record contains many characters.
+";
+        let err = parse_cobol_structure(content, &ParseOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "15:8: error: RECORD of Bad-File needs an integer\n1 error, 0 warnings");
+
+        let content = &content[..content.find("       fd  Bad-File").unwrap()];
+        let structure = parse_cobol_structure(content, &ParseOptions::default()).unwrap();
+        let cust = &structure.files[0];
+        assert_eq!(cust.name, "Cust-File");
+        assert_eq!(
+            cust.record_contains,
+            Some(RecordContains { max: Some(80), ..Default::default() })
+        );
+        assert_eq!(
+            cust.block_contains,
+            Some(BlockContains { min: None, max: 10, unit: BlockUnit::Records })
+        );
+        assert_eq!(cust.recording_mode.as_deref(), Some("F"));
+        assert_eq!(cust.label_records.as_deref(), Some("STANDARD"));
+
+        assert_eq!(
+            structure.files[1].record_contains,
+            Some(RecordContains {
+                min: Some(0),
+                max: Some(255),
+                depending_on: Some("Carlock-Rec-Len".to_string()),
+                varying: true,
+            })
+        );
+        assert_eq!(
+            structure.files[2].record_contains,
+            Some(RecordContains { min: Some(20), max: Some(40), ..Default::default() })
+        );
+        assert_eq!(structure.root_fields[2].file_description.as_deref(), Some("Sort-File"));
+    }
+}
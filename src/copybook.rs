@@ -0,0 +1,386 @@
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::Diagnostics;
+use crate::source_format::{SourceEntry, SourceFormat, check_indicators, join_entries, read_source};
+
+/// Extensions tried, in order, when a COPY names a copybook without one.
+const COPYBOOK_EXTENSIONS: &[&str] = &["cpy", "CPY", "cbl", "CBL", "cob", "COB", "fd", "FD", "ds", "DS"];
+
+/// A parsed `COPY name [OF|IN library] [REPLACING ...]` statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CopyStatement {
+    name: String,
+    library: Option<String>,
+    replacing: Vec<Replacement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Replacement {
+    /// `==pseudo-text== BY ==pseudo-text==`, matched anywhere in the text.
+    Text { from: String, to: String },
+    /// `word BY word` or `"literal" BY "literal"`, matched as a whole word.
+    Word { from: String, to: String },
+    /// `LEADING ==x== BY ==y==`, matched at the start of a word.
+    Leading { from: String, to: String },
+    /// `TRAILING ==x== BY ==y==`, matched at the end of a word.
+    Trailing { from: String, to: String },
+}
+
+/// Finds copybooks named by COPY statements and splices their entries in.
+pub struct CopybookResolver<'a> {
+    include_dirs: &'a [PathBuf],
+    format: SourceFormat,
+}
+
+impl<'a> CopybookResolver<'a> {
+    pub fn new(include_dirs: &'a [PathBuf], format: SourceFormat) -> Self {
+        CopybookResolver { include_dirs, format }
+    }
+
+    /// Replaces every COPY statement in `entries`, read from `origin`, with the
+    /// entries of the copybook it names, recursively. A COPY that cannot be
+    /// expanded is reported to `diagnostics` and left out.
+    pub fn expand(&self, entries: Vec<SourceEntry>, origin: Option<&Path>, diagnostics: &mut Diagnostics) -> Vec<SourceEntry> {
+        self.expand_nested(entries, origin, &mut Vec::new(), diagnostics)
+    }
+
+    fn expand_nested(
+        &self,
+        entries: Vec<SourceEntry>,
+        origin: Option<&Path>,
+        active: &mut Vec<PathBuf>,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<SourceEntry> {
+        let mut expanded = Vec::new();
+
+        for entry in entries {
+            let file = entry.file.clone().or_else(|| origin.map(|p| p.display().to_string()));
+            let mut report = |message: String| {
+                diagnostics.error(file.as_deref(), entry.line_number, Some(entry.column), message)
+            };
+            let copy = match parse_copy_statement(&entry.text) {
+                Ok(Some(copy)) => copy,
+                Ok(None) => {
+                    expanded.push(entry);
+                    continue;
+                }
+                Err(err) => {
+                    report(err.to_string());
+                    continue;
+                }
+            };
+            let Some(path) = self.resolve(&copy, origin) else {
+                report(format!("COPY {} not found", copy.name));
+                continue;
+            };
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if active.contains(&canonical) {
+                let chain: Vec<String> = active.iter().chain([&canonical]).map(|p| p.display().to_string()).collect();
+                report(format!("COPY cycle: {}", chain.join(" -> ")));
+                continue;
+            }
+
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(err) => {
+                    report(format!("failed to read copybook {}: {}", path.display(), err));
+                    continue;
+                }
+            };
+            let lines = read_source(&content, self.format);
+            let copybook = path.display().to_string();
+            check_indicators(&lines, Some(&copybook), diagnostics);
+            let mut nested = join_entries(&lines);
+            for nested_entry in &mut nested {
+                nested_entry.file = Some(copybook.clone());
+            }
+
+            active.push(canonical);
+            let nested = self.expand_nested(nested, Some(&path), active, diagnostics);
+            active.pop();
+
+            for mut nested_entry in nested {
+                for replacement in &copy.replacing {
+                    nested_entry.text = replacement.apply(&nested_entry.text);
+                }
+                expanded.push(nested_entry);
+            }
+        }
+
+        expanded
+    }
+
+    /// Looks for the copybook next to the including file, then as written, then
+    /// in each include directory, trying the usual extensions when it has none.
+    fn resolve(&self, copy: &CopyStatement, origin: Option<&Path>) -> Option<PathBuf> {
+        let relative = match &copy.library {
+            Some(library) => Path::new(library).join(&copy.name),
+            None => PathBuf::from(&copy.name),
+        };
+
+        let mut bases: Vec<PathBuf> = Vec::new();
+        if let Some(dir) = origin.and_then(Path::parent) {
+            bases.push(dir.to_path_buf());
+        }
+        bases.push(PathBuf::new());
+        bases.extend(self.include_dirs.iter().cloned());
+
+        for base in bases {
+            let candidate = base.join(&relative);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+            if candidate.extension().is_none() {
+                for extension in COPYBOOK_EXTENSIONS {
+                    let with_extension = candidate.with_extension(extension);
+                    if with_extension.is_file() {
+                        return Some(with_extension);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Replacement {
+    fn apply(&self, text: &str) -> String {
+        let (pattern, to) = match self {
+            Replacement::Text { from, to } => {
+                let words: Vec<String> = from.split_whitespace().map(regex::escape).collect();
+                (format!("(?i)()({})()", words.join(r"\s+")), to)
+            }
+            Replacement::Word { from, to } => (format!(r"(?i)(^|[^\w-])({})($|[^\w-])", regex::escape(from)), to),
+            Replacement::Leading { from, to } => (format!(r"(?i)(^|[^\w-])({})([\w-])", regex::escape(from)), to),
+            Replacement::Trailing { from, to } => (format!(r"(?i)([\w-])({})($|[^\w-])", regex::escape(from)), to),
+        };
+        let regex = Regex::new(&pattern).expect("escaped replacement pattern is valid");
+        regex
+            .replace_all(text, |caps: &regex::Captures| format!("{}{}{}", &caps[1], to, &caps[3]))
+            .into_owned()
+    }
+}
+
+/// Recognizes a COPY statement, returning `None` for any other entry and an
+/// error for a COPY statement that cannot be read.
+fn parse_copy_statement(text: &str) -> Result<Option<CopyStatement>> {
+    let tokens = copy_tokens(text);
+    if !tokens.first().is_some_and(|t| t.eq_ignore_ascii_case("copy")) {
+        return Ok(None);
+    }
+
+    let mut words = tokens.into_iter().skip(1).peekable();
+    let Some(name) = words.next().map(|n| unquote(&n)) else {
+        bail!("COPY needs a copybook name");
+    };
+    let mut library = None;
+    let mut replacing = Vec::new();
+
+    while let Some(word) = words.next() {
+        match word.to_ascii_uppercase().as_str() {
+            "OF" | "IN" => match words.next() {
+                Some(l) => library = Some(unquote(&l)),
+                None => bail!("COPY {} {} needs a library name", name, word),
+            },
+            "SUPPRESS" => {
+                words.next_if(|w| w.eq_ignore_ascii_case("printing"));
+            }
+            "REPLACING" => {
+                while let Some(first) = words.next() {
+                    let (kind, from) = match first.to_ascii_uppercase().as_str() {
+                        "LEADING" | "TRAILING" => match words.next() {
+                            Some(from) => (first.to_ascii_uppercase(), from),
+                            None => bail!("REPLACING {} of COPY {} needs an operand", first, name),
+                        },
+                        _ => (String::new(), first),
+                    };
+                    let to = match (words.next_if(|w| w.eq_ignore_ascii_case("by")), words.next()) {
+                        (Some(_), Some(to)) => to,
+                        _ => bail!("REPLACING {} of COPY {} needs BY and a replacement", from, name),
+                    };
+                    let is_pseudo_text = from.starts_with("==");
+                    let (from, to) = (pseudo_text(&from), pseudo_text(&to));
+                    replacing.push(match kind.as_str() {
+                        "LEADING" => Replacement::Leading { from, to },
+                        "TRAILING" => Replacement::Trailing { from, to },
+                        _ if is_pseudo_text => Replacement::Text { from, to },
+                        _ => Replacement::Word { from, to },
+                    });
+                }
+            }
+            _ => bail!("unexpected {} in COPY {}", word, name),
+        }
+    }
+
+    Ok(Some(CopyStatement {
+        name,
+        library,
+        replacing,
+    }))
+}
+
+/// Splits a COPY statement into words, keeping `==pseudo-text==` and quoted
+/// literals whole and dropping the terminating period.
+fn copy_tokens(text: &str) -> Vec<String> {
+    let text = text.trim().strip_suffix('.').unwrap_or(text.trim());
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let end = if let Some(after) = rest.strip_prefix("==") {
+            after.find("==").map(|i| i + 4).unwrap_or(rest.len())
+        } else if rest.starts_with('"') || rest.starts_with('\'') {
+            let quote = rest.chars().next().unwrap();
+            rest[1..].find(quote).map(|i| i + 2).unwrap_or(rest.len())
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+        tokens.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+
+    tokens
+}
+
+/// Strips the `==` delimiters from pseudo-text, leaving words and literals as they are.
+fn pseudo_text(operand: &str) -> String {
+    match operand.strip_prefix("==").and_then(|o| o.strip_suffix("==")) {
+        Some(text) => text.trim().to_string(),
+        None => operand.to_string(),
+    }
+}
+
+fn unquote(word: &str) -> String {
+    word.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_copybook(dir: &Path, name: &str, content: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mkrl-copybook-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entries(text: &str) -> Vec<SourceEntry> {
+        join_entries(&read_source(text, SourceFormat::Fixed))
+    }
+
+    #[test]
+    fn test_parse_copy_statement() {
+        let copy = parse_copy_statement("COPY CUSTREC OF COPYLIB REPLACING ==:PFX:== BY ==CM== OLD-NAME BY NEW-NAME.").unwrap().unwrap();
+        assert_eq!(copy.name, "CUSTREC");
+        assert_eq!(copy.library, Some("COPYLIB".to_string()));
+        assert_eq!(
+            copy.replacing,
+            vec![
+                Replacement::Text { from: ":PFX:".to_string(), to: "CM".to_string() },
+                Replacement::Word { from: "OLD-NAME".to_string(), to: "NEW-NAME".to_string() },
+            ]
+        );
+
+        let copy = parse_copy_statement("copy \"../CPY/SYSDATES.FD\".").unwrap().unwrap();
+        assert_eq!(copy.name, "../CPY/SYSDATES.FD");
+        assert!(parse_copy_statement("01  Copy-Count pic 9.").unwrap().is_none());
+
+        let err = parse_copy_statement("COPY CUSTREC REPLACING ==:PFX:== ==CM==.").unwrap_err();
+        assert_eq!(err.to_string(), "REPLACING ==:PFX:== of COPY CUSTREC needs BY and a replacement");
+        assert!(parse_copy_statement("COPY CUSTREC REPLACING LEADING.").is_err());
+        assert!(parse_copy_statement("COPY.").is_err());
+
+        let err = parse_copy_statement("COPY CUSTREC REPLACIN ==A== BY ==B==.").unwrap_err();
+        assert_eq!(err.to_string(), "unexpected REPLACIN in COPY CUSTREC");
+        assert!(parse_copy_statement("COPY CUSTREC SUPPRESS PRINTING.").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_replacements() {
+        let text = "05  :PFX:-Name pic x(20). 05  Old-Name-2 pic x. 05 Old-Name pic x.";
+        let pseudo = Replacement::Text { from: ":PFX:".to_string(), to: "CM".to_string() };
+        assert_eq!(pseudo.apply(text), "05  CM-Name pic x(20). 05  Old-Name-2 pic x. 05 Old-Name pic x.");
+
+        let word = Replacement::Word { from: "old-name".to_string(), to: "Cust-Name".to_string() };
+        assert_eq!(word.apply(text), "05  :PFX:-Name pic x(20). 05  Old-Name-2 pic x. 05 Cust-Name pic x.");
+
+        let leading = Replacement::Leading { from: "Old".to_string(), to: "New".to_string() };
+        assert_eq!(leading.apply("05 Old-Name pic x."), "05 New-Name pic x.");
+
+        let trailing = Replacement::Trailing { from: "-Name".to_string(), to: "-Nm".to_string() };
+        assert_eq!(trailing.apply("05 Old-Name pic x."), "05 Old-Nm pic x.");
+    }
+
+    #[test]
+    fn test_expand_with_include_dirs() {
+        let dir = scratch_dir("expand");
+        let lib = dir.join("lib");
+        write_copybook(&lib, "CUSTREC.cpy", "           05  :PFX:-Id          pic x(6).\n           COPY CUSTADDR.\n");
+        write_copybook(&lib, "CUSTADDR.CPY", "           05  :PFX:-City        pic x(18).\n");
+
+        let include_dirs = vec![lib.clone()];
+        let resolver = CopybookResolver::new(&include_dirs, SourceFormat::Fixed);
+        let mut diagnostics = Diagnostics::new(false);
+        let expanded = resolver.expand(
+            entries("       01  Cust-Record.\n           COPY CUSTREC REPLACING ==:PFX:== BY ==CM==.\n"),
+            None,
+            &mut diagnostics,
+        );
+        assert!(diagnostics.into_sorted().is_empty());
+
+        let texts: Vec<&str> = expanded.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["01  Cust-Record.", "05  CM-Id          pic x(6).", "05  CM-City        pic x(18)."]);
+        assert_eq!(expanded[0].file, None);
+        assert_eq!(expanded[1].file, Some(lib.join("CUSTREC.cpy").display().to_string()));
+        assert_eq!(expanded[2].line_number, 1);
+        assert!(expanded[2].file.as_deref().unwrap().ends_with("CUSTADDR.CPY"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_cycle_is_reported() {
+        let dir = scratch_dir("cycle");
+        write_copybook(&dir, "A.cpy", "           COPY B.\n");
+        write_copybook(&dir, "B.cpy", "           COPY A.\n");
+
+        let include_dirs = vec![dir.clone()];
+        let resolver = CopybookResolver::new(&include_dirs, SourceFormat::Fixed);
+        let mut diagnostics = Diagnostics::new(false);
+        let expanded = resolver.expand(
+            entries("       01  Rec.\n           COPY A.\n           COPY MISSING.\n           COPY A REPLACING X.\n"),
+            Some(Path::new("REC.FD")),
+            &mut diagnostics,
+        );
+        assert_eq!(expanded.len(), 1);
+
+        // Every problem is reported, each where its COPY statement is
+        let a = dir.join("A.cpy").canonicalize().unwrap();
+        let b = dir.join("B.cpy").canonicalize().unwrap();
+        let rendered: Vec<String> = diagnostics.into_sorted().iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                format!(
+                    "{}:1:12: error: COPY cycle: {} -> {} -> {}",
+                    dir.join("B.cpy").display(),
+                    a.display(),
+                    b.display(),
+                    a.display()
+                ),
+                "REC.FD:3:12: error: COPY MISSING not found".to_string(),
+                "REC.FD:4:12: error: REPLACING X of COPY A needs BY and a replacement".to_string(),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
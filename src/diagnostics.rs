@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in the input, located the way compilers report them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The input or copybook the problem is in, if it came from a file.
+    pub file: Option<String>,
+    pub line: usize,
+    /// Column where the offending entry starts, when it is known.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:", self.line)?;
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        write!(f, " {}: {}", self.severity, self.message)
+    }
+}
+
+/// Collects every problem found while reading an input instead of stopping at
+/// the first one.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+    /// Treat warnings as failures too.
+    strict: bool,
+}
+
+impl Diagnostics {
+    pub fn new(strict: bool) -> Self {
+        Diagnostics {
+            items: Vec::new(),
+            strict,
+        }
+    }
+
+    pub fn error(&mut self, file: Option<&str>, line: usize, column: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Error, file, line, column, message.into());
+    }
+
+    pub fn warning(&mut self, file: Option<&str>, line: usize, column: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Warning, file, line, column, message.into());
+    }
+
+    fn push(&mut self, severity: Severity, file: Option<&str>, line: usize, column: Option<usize>, message: String) {
+        self.items.push(Diagnostic {
+            severity,
+            file: file.map(str::to_string),
+            line,
+            column,
+            message,
+        });
+    }
+
+    /// Whether anything was found that should stop the input from being laid out.
+    pub fn failed(&self) -> bool {
+        self.items
+            .iter()
+            .any(|d| d.severity == Severity::Error || self.strict)
+    }
+
+    /// The diagnostics in the order they appear in the input.
+    pub fn into_sorted(mut self) -> Vec<Diagnostic> {
+        self.items
+            .sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        self.items
+    }
+}
+
+/// The diagnostics that made an input fail, rendered one per line with a
+/// count at the end.
+#[derive(Debug, Clone)]
+pub struct DiagnosticsError(pub Vec<Diagnostic>);
+
+impl fmt::Display for DiagnosticsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.0 {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        let count = |severity| self.0.iter().filter(|d| d.severity == severity).count();
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+        write!(
+            f,
+            "{} error{}, {} warning{}",
+            errors,
+            if errors == 1 { "" } else { "s" },
+            warnings,
+            if warnings == 1 { "" } else { "s" }
+        )
+    }
+}
+
+impl std::error::Error for DiagnosticsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_like_a_compiler() {
+        let mut diagnostics = Diagnostics::new(false);
+        diagnostics.warning(Some("CUST.FD"), 12, Some(8), "missing period");
+        diagnostics.error(Some("CUST.FD"), 3, None, "USAGE COMP-3 needs a numeric PICTURE");
+        diagnostics.error(None, 5, Some(12), "unrecognized clause FOO");
+        assert!(diagnostics.failed());
+
+        let error = DiagnosticsError(diagnostics.into_sorted());
+        assert_eq!(
+            error.to_string(),
+            "5:12: error: unrecognized clause FOO
+CUST.FD:3: error: USAGE COMP-3 needs a numeric PICTURE
+CUST.FD:12:8: warning: missing period
+2 errors, 1 warning"
+        );
+    }
+
+    #[test]
+    fn test_strict_fails_on_warnings() {
+        let mut lenient = Diagnostics::new(false);
+        lenient.warning(None, 1, Some(8), "missing period");
+        assert!(!lenient.failed());
+
+        let mut strict = Diagnostics::new(true);
+        assert!(!strict.failed());
+        strict.warning(None, 1, Some(8), "missing period");
+        assert!(strict.failed());
+    }
+}
@@ -1,9 +1,10 @@
 use anyhow::Result;
-use cursive::views::{Button, Dialog, EditView, LinearLayout, Panel, SelectView, TextView};
-use cursive::view::{Nameable, Resizable};
+use cursive::views::{Button, Checkbox, Dialog, EditView, LinearLayout, Panel, SelectView, TextView};
+use cursive::view::{Nameable, Resizable, Scrollable};
 use cursive::{Cursive, CursiveExt};
 use std::path::Path;
 
+use crate::diagnostics::Diagnostic;
 use crate::{cobol_parser, record_layout};
 
 pub fn run_dialog() -> Result<()> {
@@ -43,6 +44,33 @@ fn show_file_dialog(s: &mut Cursive) {
             .child(TextView::new("Enter the path to your COBOL data structure file:"))
             .child(EditView::new().with_name("file_path").min_width(50))
             .child(TextView::new("\nSupported formats: .DS, .FD, .CBL"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().with_name("hide_filler"))
+                    .child(TextView::new(" Hide FILLER items")),
+            )
+            .child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().with_name("show_conditions"))
+                    .child(TextView::new(" Show level-88 conditions")),
+            )
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Write as: "))
+                    .child(
+                        SelectView::new()
+                            .popup()
+                            .item("Record layout (.RL)", record_layout::OutputFormat::Listing)
+                            .item("Record layout, original MKRL (.RL)", record_layout::OutputFormat::GnuCobol)
+                            .item("JSON", record_layout::OutputFormat::Json)
+                            .item("YAML", record_layout::OutputFormat::Yaml)
+                            .item("CSV", record_layout::OutputFormat::Csv)
+                            .item("TSV", record_layout::OutputFormat::Tsv)
+                            .item("Markdown", record_layout::OutputFormat::Markdown)
+                            .item("HTML", record_layout::OutputFormat::Html)
+                            .with_name("format"),
+                    ),
+            )
     )
     .title("Select Input File")
     .button("Process", |s| {
@@ -54,8 +82,24 @@ fn show_file_dialog(s: &mut Cursive) {
             show_error(s, "Please enter a file path");
             return;
         }
+
+        let layout_options = record_layout::LayoutOptions {
+            hide_filler: s
+                .call_on_name("hide_filler", |view: &mut Checkbox| view.is_checked())
+                .unwrap_or(false),
+            show_conditions: s
+                .call_on_name("show_conditions", |view: &mut Checkbox| view.is_checked())
+                .unwrap_or(false),
+            format: s
+                .call_on_name("format", |view: &mut SelectView<record_layout::OutputFormat>| {
+                    view.selection().map(|format| *format)
+                })
+                .flatten()
+                .unwrap_or_default(),
+            ..Default::default()
+        };
         
-        process_file_dialog(s, &file_path);
+        process_file_dialog(s, &file_path, &layout_options);
     })
     .button("Browse Examples", |s| {
         s.pop_layer();
@@ -66,7 +110,7 @@ fn show_file_dialog(s: &mut Cursive) {
     s.add_layer(file_dialog);
 }
 
-fn process_file_dialog(s: &mut Cursive, file_path: &str) {
+fn process_file_dialog(s: &mut Cursive, file_path: &str, layout_options: &record_layout::LayoutOptions) {
     let path = Path::new(file_path);
     
     if !path.exists() {
@@ -77,33 +121,115 @@ fn process_file_dialog(s: &mut Cursive, file_path: &str) {
     // Read and process the file
     match std::fs::read_to_string(path) {
         Ok(content) => {
-            match cobol_parser::parse_cobol_structure(&content) {
-                Ok(structure) => {
-                    match record_layout::generate_layout(&structure) {
-                        Ok(layout) => {
-                            let output_path = path.with_extension("RL");
-                            match std::fs::write(&output_path, &layout) {
-                                Ok(_) => {
-                                    show_success(s, &format!(
-                                        "Record layout generated successfully!\n\
-                                        Output file: {}\n\n{}",
-                                        output_path.display(),
-                                        layout
-                                    ));
-                                }
-                                Err(e) => show_error(s, &format!("Failed to write output file: {}", e)),
-                            }
-                        }
-                        Err(e) => show_error(s, &format!("Failed to generate layout: {}", e)),
-                    }
-                }
-                Err(e) => show_error(s, &format!("Failed to parse COBOL structure: {}", e)),
+            let options = cobol_parser::ParseOptions {
+                source_path: Some(path.to_path_buf()),
+                ..Default::default()
+            };
+            let mut structure = match cobol_parser::parse_cobol_structure(&content, &options) {
+                Ok(structure) => structure,
+                Err(e) => return show_error(s, &format!("Failed to parse COBOL structure: {}", e)),
+            };
+            let layout = match record_layout::build_layout(&structure, layout_options) {
+                Ok(layout) => layout,
+                Err(e) => return show_error(s, &format!("Failed to generate layout: {}", e)),
+            };
+            structure.diagnostics.extend(record_layout::check_record_contains(&layout, Some(file_path)));
+
+            // A whole program or a file with several record types: pick what to lay out
+            if layout.records.len() > 1 {
+                choose_records(s, path, layout, structure.diagnostics, layout_options.clone());
+            } else {
+                write_layout(s, path, &layout, &structure.diagnostics, layout_options);
             }
         }
         Err(e) => show_error(s, &format!("Failed to read file: {}", e)),
     }
 }
 
+/// Lists the records of `layout` with a checkbox each, all checked, and
+/// lays out the ones left checked.
+fn choose_records(
+    s: &mut Cursive,
+    path: &Path,
+    layout: record_layout::RecordLayout,
+    diagnostics: Vec<Diagnostic>,
+    layout_options: record_layout::LayoutOptions,
+) {
+    let mut records = LinearLayout::vertical();
+    for (index, record) in layout.records.iter().enumerate() {
+        let described_in = match (&record.file, record.section) {
+            (Some(file), _) => file.clone(),
+            (None, Some(section)) => section.to_string(),
+            (None, None) => String::new(),
+        };
+        records.add_child(
+            LinearLayout::horizontal()
+                .child(Checkbox::new().checked().with_name(format!("record_{}", index)))
+                .child(TextView::new(format!(" {:02} {:<30} {}", record.level, record.name, described_in))),
+        );
+    }
+
+    let path = path.to_path_buf();
+    let dialog = Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new("Select the records to lay out:"))
+            .child(Panel::new(records.scrollable()).max_height(20))
+    )
+    .title("Records")
+    .button("Lay Out", move |s| {
+        let checked: Vec<bool> = (0..layout.records.len())
+            .map(|index| {
+                s.call_on_name(&format!("record_{}", index), |view: &mut Checkbox| view.is_checked())
+                    .unwrap_or(false)
+            })
+            .collect();
+        let mut chosen = layout.clone();
+        let mut keep = checked.into_iter();
+        chosen.records.retain(|_| keep.next().unwrap_or(false));
+
+        if chosen.records.is_empty() {
+            show_error(s, "Please select at least one record");
+            return;
+        }
+        s.pop_layer();
+        write_layout(s, &path, &chosen, &diagnostics, &layout_options);
+    })
+    .button("Cancel", |s| { s.pop_layer(); });
+
+    s.add_layer(dialog);
+}
+
+fn write_layout(
+    s: &mut Cursive,
+    path: &Path,
+    layout: &record_layout::RecordLayout,
+    diagnostics: &[Diagnostic],
+    layout_options: &record_layout::LayoutOptions,
+) {
+    match record_layout::render_layout(layout, layout_options) {
+        Ok(output) => {
+            let output_path = path.with_extension(layout_options.format.extension());
+            match std::fs::write(&output_path, &output) {
+                Ok(_) => {
+                    let warnings: String = diagnostics
+                        .iter()
+                        .map(|d| format!("{}\n", d))
+                        .collect();
+                    show_success(s, &format!(
+                        "Record layout generated successfully!\n\
+                        Output file: {}\n\n{}{}",
+                        output_path.display(),
+                        warnings,
+                        output
+                    ));
+                }
+                Err(e) => show_error(s, &format!("Failed to write output file: {}", e)),
+            }
+        }
+        Err(e) => show_error(s, &format!("Failed to generate layout: {}", e)),
+    }
+}
+
 fn show_examples(s: &mut Cursive) {
     let mut list = SelectView::new()
         .h_align(cursive::align::HAlign::Left)
@@ -138,7 +264,7 @@ fn show_examples(s: &mut Cursive) {
         if let Some(file_name) = selection {
             let example_path = format!("MKRL-0.7.1/examples/{}", file_name);
             s.pop_layer(); // Remove examples dialog
-            process_file_dialog(s, &example_path);
+            process_file_dialog(s, &example_path, &Default::default());
         }
     })
     .button("View", |s| {
@@ -170,7 +296,7 @@ fn view_example_file(s: &mut Cursive, file_name: &str) {
         Ok(content) => {
             let view_dialog = Dialog::around(
                 Panel::new(TextView::new(content))
-                    .title(&format!("Content of {}", file_name))
+                    .title(format!("Content of {}", file_name))
                     .min_width(80)
                     .min_height(20)
             )
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::record_layout::{EntryKind, LayoutEntry, LayoutOptions, RecordLayout};
+
+/// Version of the JSON and YAML layout documents. It goes up whenever a field
+/// is removed or changes meaning; new fields may appear without a change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A layout as exported: the schema version, then the layout's own fields.
+#[derive(Debug, Serialize)]
+struct LayoutDocument<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    layout: &'a RecordLayout,
+}
+
+impl<'a> LayoutDocument<'a> {
+    fn new(layout: &'a RecordLayout) -> Self {
+        LayoutDocument {
+            schema_version: SCHEMA_VERSION,
+            layout,
+        }
+    }
+}
+
+pub fn to_json(layout: &RecordLayout) -> Result<String> {
+    let mut json = serde_json::to_string_pretty(&LayoutDocument::new(layout))?;
+    json.push('\n');
+    Ok(json)
+}
+
+pub fn to_yaml(layout: &RecordLayout) -> Result<String> {
+    Ok(serde_yaml::to_string(&LayoutDocument::new(layout))?)
+}
+
+/// Renders one row per item, giving where it starts and ends in its record
+/// (1-based, as in the listing), what it holds, and the tables it repeats in.
+/// Items in a table are given for their first occurrence.
+pub fn to_delimited(layout: &RecordLayout, delimiter: char, options: &LayoutOptions) -> String {
+    let mut output = String::new();
+    if !options.omit_header {
+        let header = [
+            "Record", "Qualified Name", "Name", "Level", "Start", "End", "Length", "Picture", "Usage", "Digits",
+            "Scale", "Signed", "Occurs",
+        ];
+        push_row(&mut output, &header.map(String::from), delimiter);
+    }
+
+    for record in &layout.records {
+        for entry in &record.entries {
+            if entry.kind != EntryKind::Item || (options.hide_filler && entry.filler) {
+                continue;
+            }
+            let picture = entry.picture.as_ref();
+            push_row(
+                &mut output,
+                &[
+                    record.name.clone(),
+                    entry.path.join("."),
+                    entry.name.clone(),
+                    format!("{:02}", entry.level),
+                    (entry.offset + 1).to_string(),
+                    (entry.offset + entry.storage_length).to_string(),
+                    entry.storage_length.to_string(),
+                    picture.map(|p| p.text.clone()).unwrap_or_default(),
+                    entry.usage.clone().unwrap_or_default(),
+                    picture.map(|p| p.digits.to_string()).unwrap_or_default(),
+                    picture.map(|p| p.scale.to_string()).unwrap_or_default(),
+                    picture.map(|p| if p.signed { "Y" } else { "N" }.to_string()).unwrap_or_default(),
+                    occurs_path(&record.entries, entry),
+                ],
+                delimiter,
+            );
+        }
+    }
+    output
+}
+
+/// The tables an item repeats in, outermost first, as `Name(max)` or
+/// `Name(min:max)`.
+fn occurs_path(entries: &[LayoutEntry], entry: &LayoutEntry) -> String {
+    let mut tables = Vec::new();
+    let mut current = Some(entry);
+    while let Some(item) = current {
+        if let Some(occurs) = &item.occurs {
+            tables.push(match occurs.min {
+                Some(min) => format!("{}({}:{})", item.name, min, occurs.max),
+                None => format!("{}({})", item.name, occurs.max),
+            });
+        }
+        current = item.parent.map(|parent| &entries[parent]);
+    }
+    tables.reverse();
+    tables.join(" ")
+}
+
+/// Writes one row, quoting fields that hold the delimiter, a quote or a line
+/// break.
+fn push_row(output: &mut String, fields: &[String], delimiter: char) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            output.push(delimiter);
+        }
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            output.push('"');
+            output.push_str(&field.replace('"', "\"\""));
+            output.push('"');
+        } else {
+            output.push_str(field);
+        }
+    }
+    output.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record_layout::{LayoutOptions, build_layout};
+
+    fn sample_layout() -> RecordLayout {
+        let content = "       fd  Cust-File record contains 15 characters.
+       01  Cust-Rec.
+           05  Cust-Id         pic x(6).
+           05  Cust-Balance    pic s9(7)v99 comp-3.
+           05  Cust-History occurs 2 times.
+               10  Cust-Month  pic 99.
+           05  Cust-Shown      pic zz,zz9.
+           05  Cust-Adjust     pic s9(3) sign leading separate.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        build_layout(&structure, &LayoutOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_json_document() {
+        let json: serde_json::Value = serde_json::from_str(&to_json(&sample_layout()).unwrap()).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["name"], "Cust-Rec");
+        assert_eq!(json["files"][0]["record_contains"]["max"], 15);
+
+        let record = &json["records"][0];
+        assert_eq!(record["length"], 25);
+        let balance = &record["entries"][2];
+        assert_eq!(balance["path"], serde_json::json!(["Cust-Rec", "Cust-Balance"]));
+        assert_eq!((balance["offset"].as_u64(), balance["storage_length"].as_u64()), (Some(6), Some(5)));
+        assert_eq!(balance["usage"], "COMP-3");
+        assert_eq!(balance["picture"]["scale"], 2);
+        assert_eq!(balance["sign"], serde_json::Value::Null);
+
+        let month = &record["entries"][4];
+        assert_eq!(month["offset"], 11);
+        assert_eq!(month["parent"], 3);
+        assert_eq!(month["dimensions"][0]["max"], 2);
+
+        let adjust = &record["entries"][6];
+        assert_eq!((adjust["offset"].as_u64(), adjust["storage_length"].as_u64()), (Some(21), Some(4)));
+        assert_eq!(adjust["sign"], serde_json::json!({"position": "Leading", "separate": true}));
+    }
+
+    #[test]
+    fn test_delimited_rows() {
+        let layout = sample_layout();
+        let csv = to_delimited(&layout, ',', &LayoutOptions::default());
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "Record,Qualified Name,Name,Level,Start,End,Length,Picture,Usage,Digits,Scale,Signed,Occurs");
+        assert_eq!(lines[1], "Cust-Rec,Cust-Rec,Cust-Rec,01,1,25,25,,,,,,");
+        assert_eq!(lines[3], "Cust-Rec,Cust-Rec.Cust-Balance,Cust-Balance,05,7,11,5,s9(7)v99,COMP-3,9,2,Y,");
+        assert_eq!(lines[5], "Cust-Rec,Cust-Rec.Cust-History.Cust-Month,Cust-Month,10,12,13,2,99,,2,0,N,Cust-History(2)");
+
+        let options = LayoutOptions { omit_header: true, ..Default::default() };
+        let tsv = to_delimited(&layout, '\t', &options);
+        assert!(tsv.starts_with("Cust-Rec\tCust-Rec\tCust-Rec\t01\t1\t25\t25\t"));
+        assert!(tsv.contains("\tzz,zz9\t"));
+
+        // A delimiter inside a field gets the field quoted
+        assert!(csv.contains(",\"zz,zz9\","));
+    }
+
+    #[test]
+    fn test_yaml_document() {
+        let yaml = to_yaml(&sample_layout()).unwrap();
+        assert!(yaml.starts_with("schema_version: 1\nname: Cust-Rec\n"));
+
+        let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(value["records"][0]["entries"][1]["name"].as_str(), Some("Cust-Id"));
+        assert_eq!(value["records"][0]["entries"][1]["storage_length"].as_u64(), Some(6));
+    }
+}
@@ -1,10 +1,16 @@
 mod cobol_parser;
+mod copybook;
+mod diagnostics;
+mod export;
+mod markup;
+mod picture;
 mod record_layout;
+mod source_format;
 mod dialog;
 
 use anyhow::Result;
 use clap::{Arg, Command};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<()> {
     let matches = Command::new("mkrl-rust")
@@ -24,6 +30,83 @@ fn main() -> Result<()> {
                 .help("Launch interactive dialog interface")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("source-format")
+                .long("source-format")
+                .help("Reference format of the input source")
+                .value_name("FORMAT")
+                .value_parser(["fixed", "free"])
+                .default_value("fixed"),
+        )
+        .arg(
+            Arg::new("include")
+                .short('I')
+                .long("include")
+                .help("Directory to search for COPY copybooks (repeatable)")
+                .value_name("DIR")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Treat warnings about the input as errors")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list-records")
+                .long("list-records")
+                .help("List the records the input describes instead of laying them out")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("record")
+                .short('r')
+                .long("record")
+                .help("Lay out only the named record (repeatable)")
+                .value_name("NAME")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("hide-filler")
+                .long("hide-filler")
+                .help("Leave FILLER and unnamed items out of the record layout")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("show-conditions")
+                .long("show-conditions")
+                .help("List level-88 condition names beneath their items")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("align")
+                .long("align")
+                .help("Alignment of SYNCHRONIZED items")
+                .value_name("POLICY")
+                .value_parser(["natural", "word", "none"])
+                .default_value("natural"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Write the layout as an .RL listing (gnucobol: exactly as the original MKRL does), JSON or YAML for other programs, CSV or TSV for spreadsheets, or Markdown or HTML for publishing")
+                .value_name("FORMAT")
+                .value_parser(["rl", "gnucobol", "json", "yaml", "csv", "tsv", "markdown", "html"])
+                .default_value("rl"),
+        )
+        .arg(
+            Arg::new("delimiter")
+                .long("delimiter")
+                .help("Field separator for --format csv or tsv")
+                .value_name("CHAR")
+                .value_parser(clap::value_parser!(char)),
+        )
+        .arg(
+            Arg::new("no-header")
+                .long("no-header")
+                .help("Leave the column names out of --format csv or tsv")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("no-remove")
                 .long("no-remove")
@@ -39,15 +122,43 @@ fn main() -> Result<()> {
         // CLI mode
         let input_file = matches.get_one::<String>("input").unwrap();
         let keep_temp = matches.get_flag("no-remove");
+        let options = cobol_parser::ParseOptions {
+            source_format: matches.get_one::<String>("source-format").unwrap().parse()?,
+            source_path: Some(PathBuf::from(input_file)),
+            include_dirs: matches
+                .get_many::<String>("include")
+                .unwrap_or_default()
+                .map(PathBuf::from)
+                .collect(),
+            strict: matches.get_flag("strict"),
+        };
+        
+        let layout_options = record_layout::LayoutOptions {
+            hide_filler: matches.get_flag("hide-filler"),
+            show_conditions: matches.get_flag("show-conditions"),
+            alignment: matches.get_one::<String>("align").unwrap().parse()?,
+            format: matches.get_one::<String>("format").unwrap().parse()?,
+            delimiter: matches.get_one::<char>("delimiter").copied(),
+            omit_header: matches.get_flag("no-header"),
+        };
+        
+        let records: Vec<String> = matches.get_many::<String>("record").unwrap_or_default().cloned().collect();
         
         println!("Processing COBOL data structure: {}", input_file);
-        process_file(input_file, keep_temp)?;
+        process_file(input_file, &options, &layout_options, &records, matches.get_flag("list-records"), keep_temp)?;
     }
 
     Ok(())
 }
 
-fn process_file(input_path: &str, _keep_temp: bool) -> Result<()> {
+fn process_file(
+    input_path: &str,
+    options: &cobol_parser::ParseOptions,
+    layout_options: &record_layout::LayoutOptions,
+    records: &[String],
+    list_records: bool,
+    _keep_temp: bool,
+) -> Result<()> {
     let path = Path::new(input_path);
     
     if !path.exists() {
@@ -56,16 +167,33 @@ fn process_file(input_path: &str, _keep_temp: bool) -> Result<()> {
 
     // Read and parse the COBOL data structure
     let content = std::fs::read_to_string(path)?;
-    let data_structure = cobol_parser::parse_cobol_structure(&content)?;
+    let mut data_structure = cobol_parser::parse_cobol_structure(&content, options)?;
+    let mut layout = record_layout::build_layout(&data_structure, layout_options)?;
+    let size_warnings = record_layout::check_record_contains(&layout, Some(input_path));
+    if options.strict && !size_warnings.is_empty() {
+        return Err(diagnostics::DiagnosticsError(size_warnings).into());
+    }
+    data_structure.diagnostics.extend(size_warnings);
+    for diagnostic in &data_structure.diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+
+    if list_records {
+        print!("{}", record_layout::list_records(&layout));
+        return Ok(());
+    }
+    if !records.is_empty() {
+        layout.retain_records(records)?;
+    }
     
-    // Generate the record layout
-    let layout = record_layout::generate_layout(&data_structure)?;
+    // Render the record layout
+    let output = record_layout::render_layout(&layout, layout_options)?;
     
     // Determine output file name
-    let output_path = path.with_extension("RL");
+    let output_path = path.with_extension(layout_options.format.extension());
     
     // Write the record layout
-    std::fs::write(&output_path, layout)?;
+    std::fs::write(&output_path, output)?;
     
     println!("Record layout written to: {}", output_path.display());
     
@@ -0,0 +1,295 @@
+use crate::cobol_parser::{SignPosition, Synchronized};
+use crate::record_layout::{EntryKind, LayoutEntry, LayoutOptions, LayoutRecord, RecordLayout};
+
+const COLUMNS: [&str; 8] = ["Level", "Name", "Picture", "Usage", "Start", "Length", "Occurs", "Notes"];
+
+/// Renders `layout` as Markdown: a heading and a table per record, with
+/// names indented under their groups.
+pub fn to_markdown(layout: &RecordLayout, options: &LayoutOptions) -> String {
+    let mut output = format!("# {}\n", markdown_escape(&layout.name));
+
+    for record in &layout.records {
+        output.push_str(&format!("\n## {}\n\n{}\n\n", markdown_escape(&record.name), record_summary(record)));
+        output.push_str(&format!("| {} |\n", COLUMNS.join(" | ")));
+        output.push_str(&format!("|{}\n", "---|".repeat(COLUMNS.len())));
+        for entry in &record.entries {
+            if options.hide_filler && entry.filler {
+                continue;
+            }
+            let mut cells = cells(entry, options).map(|cell| markdown_escape(&cell));
+            cells[1] = format!("{}{}", "&nbsp;&nbsp;".repeat(entry.path.len().saturating_sub(1)), cells[1]);
+            output.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+    output
+}
+
+/// Renders `layout` as a standalone HTML page. Groups fold open and shut,
+/// and every item has an anchor to link to.
+pub fn to_html(layout: &RecordLayout, options: &LayoutOptions) -> String {
+    let title = html_escape(&layout.name);
+    let mut output = format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>{} record layout</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+.row {{ display: grid; grid-template-columns: 4em 22em 10em 8em 5em 5em 14em 1fr; gap: 0.5em; padding: 0.15em 0; }}
+.row span {{ overflow-wrap: anywhere; }}
+.head {{ font-weight: bold; border-bottom: 1px solid #888; }}
+.number {{ text-align: right; font-variant-numeric: tabular-nums; }}
+summary.row {{ cursor: pointer; }}
+details > details, details > div {{ margin-left: 1.5em; }}
+:target {{ background: #ffd; }}
+a {{ color: inherit; text-decoration: none; }}
+</style>
+</head>
+<body>
+<h1>{}</h1>
+",
+        title, title
+    );
+
+    for record in &layout.records {
+        output.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n<p>{}</p>\n",
+            html_escape(&record.name),
+            html_escape(&record_summary(record))
+        ));
+        output.push_str("<div class=\"row head\">");
+        for column in COLUMNS {
+            output.push_str(&format!("<span>{}</span>", column));
+        }
+        output.push_str("</div>\n");
+
+        let mut anchors = Vec::new();
+        for (index, entry) in record.entries.iter().enumerate() {
+            if entry.parent.is_none() {
+                push_html_entry(&mut output, record, index, &mut anchors, options);
+            }
+        }
+        output.push_str("</section>\n");
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Writes an entry as a row, or a group as a `<details>` holding its items.
+fn push_html_entry(
+    output: &mut String,
+    record: &LayoutRecord,
+    index: usize,
+    anchors: &mut Vec<String>,
+    options: &LayoutOptions,
+) {
+    let entry = &record.entries[index];
+    let children: Vec<usize> = (index + 1..record.entries.len())
+        .filter(|&child| record.entries[child].parent == Some(index))
+        .collect();
+
+    // A hidden FILLER group still shows what is under it
+    if options.hide_filler && entry.filler {
+        for child in children {
+            push_html_entry(output, record, child, anchors, options);
+        }
+        return;
+    }
+
+    // Anchors follow the qualified name; FILLERs repeat it, so they are numbered
+    let mut anchor = entry.path.join(".");
+    if anchors.contains(&anchor) {
+        anchor = format!("{}.{}", anchor, index);
+    }
+    anchors.push(anchor.clone());
+    let anchor = html_escape(&anchor);
+
+    let mut row = String::new();
+    for (column, cell) in cells(entry, options).iter().enumerate() {
+        let class = if matches!(column, 4 | 5) { " class=\"number\"" } else { "" };
+        if column == 1 {
+            row.push_str(&format!("<span><a href=\"#{}\">{}</a></span>", anchor, html_escape(cell)));
+        } else {
+            row.push_str(&format!("<span{}>{}</span>", class, html_escape(cell)));
+        }
+    }
+
+    if children.is_empty() {
+        output.push_str(&format!("<div class=\"row\" id=\"{}\">{}</div>\n", anchor, row));
+    } else {
+        output.push_str(&format!("<details open id=\"{}\">\n<summary class=\"row\">{}</summary>\n", anchor, row));
+        for child in children {
+            push_html_entry(output, record, child, anchors, options);
+        }
+        output.push_str("</details>\n");
+    }
+}
+
+/// What is known about a record as a whole, in one line.
+fn record_summary(record: &LayoutRecord) -> String {
+    let mut parts = vec![format!("Level {:02}", record.level)];
+    if let Some(section) = record.section {
+        parts.push(format!("{} SECTION", section));
+    }
+    if let Some(file) = &record.file {
+        parts.push(format!("file {}", file));
+    }
+    if let Some(target) = &record.redefines {
+        parts.push(format!("redefines {}", target));
+    }
+    parts.push(match record.min_length {
+        Some(min) => format!("{} to {} bytes", min, record.length),
+        None => format!("{} bytes", record.length),
+    });
+    parts.join(", ")
+}
+
+/// The cells of an entry's row, in the order of `COLUMNS`.
+fn cells(entry: &LayoutEntry, options: &LayoutOptions) -> [String; 8] {
+    let occurs = match &entry.occurs {
+        Some(occurs) => {
+            let count = match occurs.min {
+                Some(min) => format!("{} to {}", min, occurs.max),
+                None => occurs.max.to_string(),
+            };
+            match &occurs.depending_on {
+                Some(object) => format!("{} depending on {}", count, object),
+                None => count,
+            }
+        }
+        None => String::new(),
+    };
+
+    [
+        format!("{:02}", entry.level),
+        entry.name.clone(),
+        entry.picture.as_ref().map(|p| p.text.clone()).unwrap_or_default(),
+        entry.usage.clone().unwrap_or_default(),
+        // 1-based, like the listing's Pos and the delimited exports' Start
+        (entry.offset + 1).to_string(),
+        entry.storage_length.to_string(),
+        occurs,
+        notes(entry, options).join("; "),
+    ]
+}
+
+/// The clauses and remarks that have no column of their own.
+fn notes(entry: &LayoutEntry, options: &LayoutOptions) -> Vec<String> {
+    let mut notes = Vec::new();
+    match entry.kind {
+        EntryKind::SlackBytes => notes.push("slack bytes to align the next item".to_string()),
+        EntryKind::Renames => notes.push("RENAMES".to_string()),
+        EntryKind::Item => {}
+    }
+    if let Some(target) = &entry.redefines {
+        notes.push(format!("REDEFINES {}", target));
+    }
+    if let Some(sign) = entry.sign {
+        let position = match sign.position {
+            SignPosition::Leading => "LEADING",
+            SignPosition::Trailing => "TRAILING",
+        };
+        notes.push(format!("SIGN {}{}", position, if sign.separate { " SEPARATE" } else { "" }));
+    }
+    match entry.synchronized {
+        Some(Synchronized::Plain) => notes.push("SYNC".to_string()),
+        Some(Synchronized::Left) => notes.push("SYNC LEFT".to_string()),
+        Some(Synchronized::Right) => notes.push("SYNC RIGHT".to_string()),
+        None => {}
+    }
+    for key in &entry.keys {
+        let order = if key.ascending { "ASCENDING" } else { "DESCENDING" };
+        notes.push(format!("{} KEY {}", order, key.name));
+    }
+    if !entry.indexed_by.is_empty() {
+        notes.push(format!("INDEXED BY {}", entry.indexed_by.join(", ")));
+    }
+    if entry.variable_offset {
+        notes.push("start varies with OCCURS DEPENDING ON".to_string());
+    }
+    if options.show_conditions {
+        for condition in &entry.conditions {
+            let values: Vec<String> = condition
+                .values
+                .iter()
+                .map(|value| match &value.thru {
+                    Some(thru) => format!("{} THRU {}", value.from, thru),
+                    None => value.from.clone(),
+                })
+                .collect();
+            notes.push(format!("88 {} VALUE {}", condition.name, values.join(", ")));
+        }
+    }
+    notes
+}
+
+fn markdown_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record_layout::build_layout;
+
+    fn sample_layout() -> RecordLayout {
+        let content = "       fd  Cust-File.
+       01  Cust-Rec.
+           05  Cust-Id         pic x(6).
+           05  Cust-Status     pic x.
+               88  Cust-Open           value 'O'.
+           05  Cust-History occurs 2 times indexed by Hist-Idx.
+               10  Cust-Month  pic 99.
+               10              pic x(3).
+               10              pic x(3).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        build_layout(&structure, &LayoutOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let options = LayoutOptions { show_conditions: true, ..Default::default() };
+        let markdown = to_markdown(&sample_layout(), &options);
+        let lines: Vec<&str> = markdown.lines().collect();
+
+        assert_eq!(lines[0], "# Cust-Rec");
+        assert_eq!(lines[2], "## Cust-Rec");
+        assert_eq!(lines[4], "Level 01, FILE SECTION, file Cust-File, 23 bytes");
+        assert_eq!(lines[6], "| Level | Name | Picture | Usage | Start | Length | Occurs | Notes |");
+        assert_eq!(lines[9], "| 05 | &nbsp;&nbsp;Cust-Id | x(6) |  | 1 | 6 |  |  |");
+        assert_eq!(lines[10], "| 05 | &nbsp;&nbsp;Cust-Status | x |  | 7 | 1 |  | 88 Cust-Open VALUE 'O' |");
+        assert_eq!(lines[11], "| 05 | &nbsp;&nbsp;Cust-History |  |  | 8 | 8 | 2 | INDEXED BY Hist-Idx |");
+        assert_eq!(lines[12], "| 10 | &nbsp;&nbsp;&nbsp;&nbsp;Cust-Month | 99 |  | 8 | 2 |  |  |");
+
+        let hidden = to_markdown(&sample_layout(), &LayoutOptions { hide_filler: true, ..Default::default() });
+        assert!(!hidden.contains("FILLER"));
+    }
+
+    #[test]
+    fn test_html_page() {
+        let html = to_html(&sample_layout(), &LayoutOptions::default());
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+        assert!(html.contains("<details open id=\"Cust-Rec\">"));
+        assert!(html.contains("<details open id=\"Cust-Rec.Cust-History\">\n<summary class=\"row\"><span>05</span>"));
+        assert!(html.contains(
+            "<div class=\"row\" id=\"Cust-Rec.Cust-History.Cust-Month\"><span>10</span>\
+             <span><a href=\"#Cust-Rec.Cust-History.Cust-Month\">Cust-Month</a></span>"
+        ));
+        // FILLERs share a qualified name, so their anchors are numbered
+        assert!(html.contains("id=\"Cust-Rec.Cust-History.FILLER\""));
+        assert!(html.contains("id=\"Cust-Rec.Cust-History.FILLER.6\""));
+        assert_eq!(html.matches("<details").count(), html.matches("</details>").count());
+    }
+}
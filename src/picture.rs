@@ -0,0 +1,327 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// The data category a PICTURE string gives an elementary item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PictureCategory {
+    Alphabetic,
+    Alphanumeric,
+    AlphanumericEdited,
+    National,
+    Numeric,
+    NumericEdited,
+    FloatingPoint,
+}
+
+/// What a PICTURE string says about an item, independent of its USAGE.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Picture {
+    pub category: PictureCategory,
+    /// Digit positions, counting 9, Z, * and floating insertion characters.
+    pub digits: u32,
+    /// Digit positions right of the decimal point; negative for trailing P scaling.
+    pub scale: i32,
+    pub signed: bool,
+    /// Bytes the item occupies with USAGE DISPLAY.
+    pub display_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    A,
+    X,
+    N,
+    Nine,
+    S,
+    V,
+    P,
+    Z,
+    Asterisk,
+    B,
+    Zero,
+    Slash,
+    Comma,
+    Period,
+    Plus,
+    Minus,
+    Cr,
+    Db,
+    Currency,
+    E,
+}
+
+/// Analyzes a PICTURE character-string such as `s9(7)v99`, `zz,zz9.99-` or `x(20)`.
+pub fn parse_picture(picture: &str) -> Result<Picture> {
+    let tokens = tokenize(picture)?;
+
+    let mut display_size = 0usize;
+    let mut digits = 0u32;
+    let mut scale = 0i32;
+    let mut signed = false;
+    let mut after_point = false;
+    let mut seen_digit = false;
+    let mut has_alpha = false;
+    let mut has_x = false;
+    let mut has_national = false;
+    let mut has_exponent = false;
+    let mut has_insertion = false;
+    let mut has_numeric_editing = false;
+    let mut floating: Option<Symbol> = None;
+
+    for &(symbol, count) in &tokens {
+        let n = count as u32;
+        match symbol {
+            Symbol::A => {
+                has_alpha = true;
+                display_size += count;
+            }
+            Symbol::X => {
+                has_x = true;
+                display_size += count;
+            }
+            Symbol::N => {
+                has_national = true;
+                display_size += count * 2;
+            }
+            Symbol::Nine | Symbol::Z | Symbol::Asterisk => {
+                if symbol != Symbol::Nine {
+                    has_numeric_editing = true;
+                }
+                if !has_exponent {
+                    digits += n;
+                    if after_point {
+                        scale += n as i32;
+                    }
+                }
+                seen_digit = true;
+                display_size += count;
+            }
+            Symbol::S => signed = true,
+            Symbol::V => after_point = true,
+            Symbol::P => {
+                // Leading P's imply a decimal point to their left, trailing ones scale up
+                if after_point || !seen_digit {
+                    after_point = true;
+                    scale += n as i32;
+                } else {
+                    scale -= n as i32;
+                }
+            }
+            Symbol::B | Symbol::Zero | Symbol::Slash => {
+                has_insertion = true;
+                display_size += count;
+            }
+            Symbol::Comma => {
+                has_numeric_editing = true;
+                display_size += count;
+            }
+            Symbol::Period => {
+                has_numeric_editing = true;
+                after_point = true;
+                display_size += count;
+            }
+            Symbol::Plus | Symbol::Minus | Symbol::Currency => {
+                has_numeric_editing = true;
+                if symbol != Symbol::Currency {
+                    signed = true;
+                }
+                // A floating insertion string holds one digit position fewer than its length
+                let positions = if floating == Some(symbol) {
+                    n
+                } else if count > 1 {
+                    floating = Some(symbol);
+                    n - 1
+                } else {
+                    0
+                };
+                if positions > 0 && !has_exponent {
+                    digits += positions;
+                    if after_point {
+                        scale += positions as i32;
+                    }
+                    seen_digit = true;
+                }
+                display_size += count;
+            }
+            Symbol::Cr | Symbol::Db => {
+                has_numeric_editing = true;
+                signed = true;
+                display_size += count * 2;
+            }
+            Symbol::E => {
+                has_exponent = true;
+                display_size += count;
+            }
+        }
+    }
+
+    let category = if has_exponent {
+        PictureCategory::FloatingPoint
+    } else if has_national {
+        PictureCategory::National
+    } else if has_x || (has_alpha && seen_digit) {
+        if has_insertion {
+            PictureCategory::AlphanumericEdited
+        } else {
+            PictureCategory::Alphanumeric
+        }
+    } else if has_alpha {
+        if has_insertion {
+            PictureCategory::AlphanumericEdited
+        } else {
+            PictureCategory::Alphabetic
+        }
+    } else if has_numeric_editing || has_insertion {
+        PictureCategory::NumericEdited
+    } else {
+        PictureCategory::Numeric
+    };
+
+    if matches!(category, PictureCategory::Numeric) && digits == 0 {
+        bail!("PICTURE {} has no digit positions", picture);
+    }
+
+    Ok(Picture {
+        category,
+        digits,
+        scale,
+        signed,
+        display_size,
+    })
+}
+
+/// Splits a PICTURE string into symbols with their repetition counts, merging
+/// adjacent repeats so `99(3)` and `9(4)` both become a single run of four.
+fn tokenize(picture: &str) -> Result<Vec<(Symbol, usize)>> {
+    let chars: Vec<char> = picture.to_ascii_uppercase().chars().collect();
+    let mut tokens: Vec<(Symbol, usize)> = Vec::new();
+    let mut i = 0;
+
+    if chars.is_empty() {
+        bail!("empty PICTURE string");
+    }
+
+    while i < chars.len() {
+        let symbol = match chars[i] {
+            'A' => Symbol::A,
+            'X' => Symbol::X,
+            'N' => Symbol::N,
+            '9' => Symbol::Nine,
+            'S' => Symbol::S,
+            'V' => Symbol::V,
+            'P' => Symbol::P,
+            'Z' => Symbol::Z,
+            '*' => Symbol::Asterisk,
+            'B' => Symbol::B,
+            '0' => Symbol::Zero,
+            '/' => Symbol::Slash,
+            ',' => Symbol::Comma,
+            '.' => Symbol::Period,
+            '+' => Symbol::Plus,
+            '-' => Symbol::Minus,
+            '$' => Symbol::Currency,
+            'E' => Symbol::E,
+            'C' if chars.get(i + 1) == Some(&'R') => {
+                i += 1;
+                Symbol::Cr
+            }
+            'D' if chars.get(i + 1) == Some(&'B') => {
+                i += 1;
+                Symbol::Db
+            }
+            c => bail!("invalid character '{}' in PICTURE {}", c, picture),
+        };
+        i += 1;
+
+        let mut count = 1;
+        if chars.get(i) == Some(&'(') {
+            let close = match chars[i..].iter().position(|&c| c == ')') {
+                Some(offset) => i + offset,
+                None => bail!("unterminated repetition count in PICTURE {}", picture),
+            };
+            let digits: String = chars[i + 1..close].iter().collect();
+            count = match digits.trim().parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => bail!("invalid repetition count ({}) in PICTURE {}", digits, picture),
+            };
+            i = close + 1;
+        }
+
+        match tokens.last_mut() {
+            Some((last, n)) if *last == symbol => *n += count,
+            _ => tokens.push((symbol, count)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_pictures() {
+        let pic = parse_picture("s99v9(3)").unwrap();
+        assert_eq!(pic.category, PictureCategory::Numeric);
+        assert_eq!(pic.digits, 5);
+        assert_eq!(pic.scale, 3);
+        assert!(pic.signed);
+        assert_eq!(pic.display_size, 5);
+
+        let pic = parse_picture("9(4)").unwrap();
+        assert_eq!((pic.digits, pic.scale, pic.signed, pic.display_size), (4, 0, false, 4));
+    }
+
+    #[test]
+    fn test_p_scaling() {
+        let pic = parse_picture("999PPP").unwrap();
+        assert_eq!((pic.digits, pic.scale, pic.display_size), (3, -3, 3));
+
+        let pic = parse_picture("PP99").unwrap();
+        assert_eq!((pic.digits, pic.scale, pic.display_size), (2, 4, 2));
+    }
+
+    #[test]
+    fn test_edited_pictures() {
+        let pic = parse_picture("zz,zz9.99-").unwrap();
+        assert_eq!(pic.category, PictureCategory::NumericEdited);
+        assert_eq!((pic.digits, pic.scale, pic.signed, pic.display_size), (7, 2, true, 10));
+
+        let pic = parse_picture("$$$,$$9.99CR").unwrap();
+        assert_eq!((pic.digits, pic.scale, pic.display_size), (7, 2, 12));
+
+        let pic = parse_picture("zzzzz9").unwrap();
+        assert_eq!((pic.digits, pic.display_size), (6, 6));
+
+        let pic = parse_picture("99/99/99").unwrap();
+        assert_eq!(pic.category, PictureCategory::NumericEdited);
+        assert_eq!(pic.display_size, 8);
+    }
+
+    #[test]
+    fn test_alphanumeric_pictures() {
+        assert_eq!(parse_picture("x(20)").unwrap().display_size, 20);
+        assert_eq!(parse_picture("a(5)").unwrap().category, PictureCategory::Alphabetic);
+        assert_eq!(parse_picture("xxbxx").unwrap().category, PictureCategory::AlphanumericEdited);
+
+        let pic = parse_picture("n(10)").unwrap();
+        assert_eq!(pic.category, PictureCategory::National);
+        assert_eq!(pic.display_size, 20);
+    }
+
+    #[test]
+    fn test_floating_point_picture() {
+        let pic = parse_picture("+9.9(5)E+99").unwrap();
+        assert_eq!(pic.category, PictureCategory::FloatingPoint);
+        assert_eq!(pic.display_size, 12);
+    }
+
+    #[test]
+    fn test_invalid_pictures() {
+        assert!(parse_picture("9(x)").is_err());
+        assert!(parse_picture("9(4").is_err());
+        assert!(parse_picture("q9").is_err());
+        assert!(parse_picture("sv").is_err());
+    }
+}
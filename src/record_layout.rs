@@ -1,227 +1,1008 @@
-use crate::cobol_parser::{CobolField, CobolStructure};
-use anyhow::Result;
+use crate::cobol_parser::{
+    CobolField, CobolStructure, ConditionName, DataSection, FileDescription, SignClause, SignPosition, Synchronized,
+    TableKey,
+};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::{export, markup};
+use crate::picture::{PictureCategory, parse_picture};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
 
-pub fn generate_layout(structure: &CobolStructure) -> Result<String> {
+/// How SYNCHRONIZED items are aligned relative to the start of their record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlignmentPolicy {
+    /// Each item on a multiple of its own size, up to 8 bytes, as 64-bit compilers do.
+    #[default]
+    Natural,
+    /// No boundary beyond 4 bytes, as on 32-bit targets and IBM fullwords.
+    Word,
+    /// SYNCHRONIZED is ignored and no slack bytes are inserted.
+    None,
+}
+
+impl std::str::FromStr for AlignmentPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "natural" => Ok(AlignmentPolicy::Natural),
+            "word" => Ok(AlignmentPolicy::Word),
+            "none" => Ok(AlignmentPolicy::None),
+            _ => bail!("unknown alignment: {} (expected natural, word or none)", s),
+        }
+    }
+}
+
+/// What a layout is written as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The `.RL` listing.
+    #[default]
+    Listing,
+    Json,
+    Yaml,
+    /// One row per item, comma separated, for spreadsheets.
+    Csv,
+    /// One row per item, tab separated.
+    Tsv,
+    /// A table per record, for wikis.
+    Markdown,
+    /// A standalone page with groups that fold open and shut.
+    Html,
+    /// The `.RL` listing exactly as the original GnuCOBOL MKRL writes it.
+    GnuCobol,
+}
+
+impl OutputFormat {
+    /// Extension of the file the layout is written to, next to the input.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Listing | OutputFormat::GnuCobol => "RL",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rl" => Ok(OutputFormat::Listing),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "gnucobol" => Ok(OutputFormat::GnuCobol),
+            _ => bail!("unknown output format: {} (expected rl, gnucobol, json, yaml, csv, tsv, markdown or html)", s),
+        }
+    }
+}
+
+/// Options controlling what the record layout lists.
+#[derive(Debug, Clone, Default)]
+pub struct LayoutOptions {
+    /// Leave FILLER and unnamed items out of the listing; they still take up positions.
+    pub hide_filler: bool,
+    /// List each item's level-88 condition names and values beneath it.
+    pub show_conditions: bool,
+    /// Where SYNCHRONIZED items get slack bytes in front of them.
+    pub alignment: AlignmentPolicy,
+    /// What the layout is written as. The JSON and YAML documents always
+    /// carry every item and condition name, whatever the listing leaves out.
+    pub format: OutputFormat,
+    /// Separator for CSV and TSV output, in place of the format's own.
+    pub delimiter: Option<char>,
+    /// Leave the column names out of CSV and TSV output.
+    pub omit_header: bool,
+}
+
+/// Every record of an input laid out: where each item starts and how much
+/// storage it takes. The `.RL` listing and every other output format are
+/// rendered from this.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordLayout {
+    /// Name of the first 01 record, which names the layout as a whole.
+    pub name: String,
+    /// The FD and SD entries the records are described under.
+    pub files: Vec<FileDescription>,
+    pub records: Vec<LayoutRecord>,
+}
+
+impl RecordLayout {
+    /// Keeps only the records named in `names`, in input order.
+    pub fn retain_records(&mut self, names: &[String]) -> Result<()> {
+        for name in names {
+            if !self.records.iter().any(|record| record.name.eq_ignore_ascii_case(name)) {
+                bail!("no record named {}", name);
+            }
+        }
+        self.records
+            .retain(|record| names.iter().any(|name| record.name.eq_ignore_ascii_case(name)));
+        Ok(())
+    }
+}
+
+/// One 01 record or 77 item, or a run of items with no 01 above them, with
+/// offsets counted from its start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutRecord {
+    pub name: String,
+    pub level: u32,
+    pub redefines: Option<String>,
+    pub section: Option<DataSection>,
+    /// FD or SD the record is described under.
+    pub file: Option<String>,
+    /// Line the record's first entry is on.
+    pub line_number: usize,
+    /// Bytes the record takes, with every table at its largest.
+    pub length: usize,
+    /// Bytes the record takes with every OCCURS DEPENDING ON table at its
+    /// smallest, when it has any.
+    pub min_length: Option<usize>,
+    /// The record's items in declaration order, each followed by the items
+    /// under it.
+    pub entries: Vec<LayoutEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryKind {
+    /// A data item from the input.
+    Item,
+    /// Bytes the compiler inserts to align the SYNCHRONIZED item after them.
+    SlackBytes,
+    /// A level-66 alias, spanning items already laid out.
+    Renames,
+}
+
+/// An item of a record and where it sits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutEntry {
+    pub kind: EntryKind,
+    pub level: u32,
+    pub name: String,
+    /// Names from the record down to the item, which qualify it uniquely.
+    pub path: Vec<String>,
+    /// Index in `entries` of the group the item belongs to.
+    pub parent: Option<usize>,
+    /// Whether the item has items under it.
+    pub group: bool,
+    /// A FILLER or unnamed item, which takes space but cannot be referred to.
+    pub filler: bool,
+    /// Bytes from the start of the record to the item's first occurrence.
+    pub offset: usize,
+    /// Bytes one occurrence of the item takes.
+    pub storage_length: usize,
+    /// Characters the item shows as when displayed, from its PICTURE.
+    pub display_length: Option<usize>,
+    pub usage: Option<String>,
+    pub picture: Option<PictureDescriptor>,
+    /// The item's own OCCURS clause.
+    pub occurs: Option<Occurs>,
+    /// Every OCCURS the item is repeated by, outermost first and ending with
+    /// its own; one subscript each.
+    pub dimensions: Vec<Occurs>,
+    /// The offset holds only while the OCCURS DEPENDING ON tables before the
+    /// item are full.
+    pub variable_offset: bool,
+    pub redefines: Option<String>,
+    pub sign: Option<SignClause>,
+    pub synchronized: Option<Synchronized>,
+    pub keys: Vec<TableKey>,
+    pub indexed_by: Vec<String>,
+    pub conditions: Vec<ConditionName>,
+}
+
+impl LayoutEntry {
+    /// Bytes the item takes counting every occurrence of it.
+    pub fn total_length(&self) -> usize {
+        self.storage_length * self.dimensions.iter().map(|d| d.max as usize).product::<usize>()
+    }
+}
+
+/// A PICTURE string and what it says about the item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PictureDescriptor {
+    /// The PICTURE string as written.
+    pub text: String,
+    pub category: PictureCategory,
+    pub digits: u32,
+    /// Digit positions right of the decimal point; negative for trailing P scaling.
+    pub scale: i32,
+    pub signed: bool,
+}
+
+/// An OCCURS clause: a fixed count, or a range whose current size the
+/// DEPENDING ON item holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Occurs {
+    /// Lower bound of an OCCURS n TO m table.
+    pub min: Option<u32>,
+    pub max: u32,
+    pub depending_on: Option<String>,
+}
+
+/// Lays out every record in `structure`. Each 01 record and 77 item becomes
+/// a record of its own, with offsets counted from its start.
+pub fn build_layout(structure: &CobolStructure, options: &LayoutOptions) -> Result<RecordLayout> {
+    // Free-format source read as fixed loses its first columns, and with them every entry
+    if structure.root_fields.is_empty() {
+        bail!("no data description entries found (if the source starts in column 1, try --source-format free)");
+    }
+    let records = record_sections(&structure.root_fields)
+        .into_iter()
+        .map(|section| build_record(section, options))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(RecordLayout {
+        name: structure.name.clone(),
+        files: structure.files.clone(),
+        records,
+    })
+}
+
+fn build_record(fields: &[CobolField], options: &LayoutOptions) -> Result<LayoutRecord> {
+    let mut builder = LayoutBuilder {
+        entries: Vec::new(),
+        variable_offset: false,
+        alignment: options.alignment,
+        strictest_boundary: 1,
+    };
+    let mut offset = 0;
+
+    for field in fields {
+        let index = builder.entries.len();
+        offset = builder.place(field, offset, None, &[], &[])?;
+        builder.place_renames(field, index)?;
+    }
+
+    let first = &fields[0];
+    let variable = builder
+        .entries
+        .iter()
+        .any(|entry| entry.occurs.as_ref().is_some_and(|occurs| occurs.depending_on.is_some()));
+    Ok(LayoutRecord {
+        name: first.name.clone(),
+        level: first.level,
+        redefines: first.redefines.clone(),
+        section: first.section,
+        file: first.file_description.clone(),
+        line_number: first.line_number,
+        length: offset,
+        min_length: variable.then(|| minimum_length(offset, &builder.entries)),
+        entries: builder.entries,
+    })
+}
+
+/// Bytes a record of `length` takes with every variable table at its
+/// smallest. Nothing but a table's own items may follow an OCCURS DEPENDING
+/// ON table, so each table shrinks the record by its unused occurrences,
+/// counted with the tables around it at their smallest too. No REDEFINES
+/// alternative holds one, so none of them overlap.
+fn minimum_length(length: usize, entries: &[LayoutEntry]) -> usize {
+    let unused: usize = entries
+        .iter()
+        .filter(|entry| entry.kind == EntryKind::Item)
+        .filter_map(|entry| {
+            let occurs = entry.occurs.as_ref()?;
+            let min = occurs.min? as usize;
+            let outer = &entry.dimensions[..entry.dimensions.len() - 1];
+            let repeats: usize = outer.iter().map(|d| d.min.unwrap_or(d.max) as usize).product();
+            Some((occurs.max as usize - min) * entry.storage_length * repeats)
+        })
+        .sum();
+    length.saturating_sub(unused)
+}
+
+/// Walks a record's items once, placing each after the ones before it.
+struct LayoutBuilder {
+    entries: Vec<LayoutEntry>,
+    /// An OCCURS DEPENDING ON table has been placed, so later offsets vary.
+    variable_offset: bool,
+    alignment: AlignmentPolicy,
+    /// The strictest boundary a SYNCHRONIZED item placed so far is aligned on.
+    strictest_boundary: usize,
+}
+
+impl LayoutBuilder {
+    /// Places `field` and everything under it at `offset`, returning the
+    /// offset just past all its occurrences.
+    fn place(
+        &mut self,
+        field: &CobolField,
+        mut offset: usize,
+        parent: Option<usize>,
+        dimensions: &[Occurs],
+        path: &[String],
+    ) -> Result<usize> {
+        let occurs = field.occurs.map(|max| Occurs {
+            min: field.occurs_min,
+            max,
+            depending_on: field.depending_on.clone(),
+        });
+        let mut item_dimensions = dimensions.to_vec();
+        item_dimensions.extend(occurs.clone());
+        let mut item_path = path.to_vec();
+        item_path.push(field.name.clone());
+
+        let group = !field.children.is_empty();
+        let length = if group { 0 } else { calculate_field_length(field)? };
+
+        // The compiler pads a SYNCHRONIZED item out to its boundary with slack bytes
+        let boundary = sync_boundary(field, length, self.alignment);
+        self.strictest_boundary = self.strictest_boundary.max(boundary);
+        let slack = (boundary - offset % boundary) % boundary;
+        if slack > 0 {
+            self.push_slack(field.level, path, parent, offset, slack, dimensions);
+            offset += slack;
+        }
+
+        let picture = match &field.picture {
+            Some(text) if !group => {
+                let details = parse_picture(text)?;
+                Some(PictureDescriptor {
+                    text: text.clone(),
+                    category: details.category,
+                    digits: details.digits,
+                    scale: details.scale,
+                    signed: details.signed,
+                })
+            }
+            _ => None,
+        };
+        let display_length = match &field.picture {
+            Some(text) if !group => Some(calculate_picture_length(text)? + separate_sign_length(field, text)?),
+            _ => None,
+        };
+
+        let index = self.entries.len();
+        self.entries.push(LayoutEntry {
+            kind: EntryKind::Item,
+            level: field.level,
+            name: field.name.clone(),
+            path: item_path.clone(),
+            parent,
+            group,
+            filler: field.is_filler(),
+            offset,
+            storage_length: length,
+            display_length,
+            usage: field.data_type.clone(),
+            picture,
+            occurs,
+            dimensions: item_dimensions.clone(),
+            variable_offset: self.variable_offset,
+            redefines: field.redefines.clone(),
+            sign: field.sign,
+            synchronized: field.synchronized,
+            keys: field.keys.clone(),
+            indexed_by: field.indexed_by.clone(),
+            conditions: field.conditions.clone(),
+        });
+
+        if group {
+            let outer_boundary = std::mem::replace(&mut self.strictest_boundary, 1);
+            let mut end = self.place_children(&field.children, offset, index, &item_dimensions, &item_path)?;
+            let boundary = self.strictest_boundary;
+            self.strictest_boundary = outer_boundary.max(boundary);
+
+            // Each occurrence of a table is padded out to the strictest boundary
+            // within it, so the next occurrence's items are aligned as well
+            let padding = (boundary - (end - offset) % boundary) % boundary;
+            if field.occurs.is_some() && padding > 0 {
+                self.push_slack(field.children[0].level, &item_path, Some(index), end, padding, &item_dimensions);
+                end += padding;
+            }
+            self.entries[index].storage_length = end - offset;
+        }
+
+        // Everything after a variable-length table moves with its DEPENDING ON object
+        if field.depending_on.is_some() {
+            self.variable_offset = true;
+        }
+
+        Ok(offset + self.entries[index].storage_length * field.occurs.unwrap_or(1) as usize)
+    }
+
+    /// Adds `length` slack bytes at `offset`, which the compiler inserts to
+    /// align a SYNCHRONIZED item.
+    fn push_slack(
+        &mut self,
+        level: u32,
+        path: &[String],
+        parent: Option<usize>,
+        offset: usize,
+        length: usize,
+        dimensions: &[Occurs],
+    ) {
+        self.entries.push(LayoutEntry {
+            kind: EntryKind::SlackBytes,
+            level,
+            name: "SLACK-BYTES".to_string(),
+            path: path.to_vec(),
+            parent,
+            group: false,
+            filler: false,
+            offset,
+            storage_length: length,
+            display_length: None,
+            usage: None,
+            picture: None,
+            occurs: None,
+            dimensions: dimensions.to_vec(),
+            variable_offset: self.variable_offset,
+            redefines: None,
+            sign: None,
+            synchronized: None,
+            keys: Vec::new(),
+            indexed_by: Vec::new(),
+            conditions: Vec::new(),
+        });
+    }
+
+    /// Places a run of sibling items. An item with a REDEFINES clause restarts
+    /// at the offset of the item it redefines, and the run continues after the
+    /// longest of the overlapping alternatives.
+    fn place_children(
+        &mut self,
+        children: &[CobolField],
+        start: usize,
+        parent: usize,
+        dimensions: &[Occurs],
+        path: &[String],
+    ) -> Result<usize> {
+        let mut placed: Vec<(&str, usize)> = Vec::new();
+        let mut offset = start;
+        let mut end = start;
+
+        for child in children {
+            if let Some(redefined) = redefined_start(&placed, child)? {
+                offset = redefined;
+            }
+            placed.push((&child.name, offset));
+            offset = self.place(child, offset, Some(parent), dimensions, path)?;
+            end = end.max(offset);
+            offset = end;
+        }
+
+        Ok(end)
+    }
+
+    /// Adds `record`'s level-66 aliases after its items. Each spans from the
+    /// start of its first renamed item to the end of the last and adds no storage.
+    fn place_renames(&mut self, record: &CobolField, index: usize) -> Result<()> {
+        for renames in &record.renames {
+            let span_of = |name: &str| {
+                self.entries[index + 1..]
+                    .iter()
+                    .find(|entry| entry.kind == EntryKind::Item && entry.name.eq_ignore_ascii_case(name))
+                    .map(|entry| {
+                        let occurs = entry.occurs.as_ref().map_or(1, |o| o.max as usize);
+                        (entry.offset, entry.offset + entry.storage_length * occurs)
+                    })
+            };
+            let last = renames.thru.as_deref().unwrap_or(&renames.from);
+            let (Some((from, from_end)), Some((_, end))) = (span_of(&renames.from), span_of(last)) else {
+                bail!("{} renames items that are not part of {}", renames.name, record.name);
+            };
+
+            self.entries.push(LayoutEntry {
+                kind: EntryKind::Renames,
+                level: 66,
+                name: renames.name.clone(),
+                path: vec![record.name.clone(), renames.name.clone()],
+                parent: Some(index),
+                group: false,
+                filler: false,
+                offset: from,
+                storage_length: end.max(from_end) - from,
+                display_length: None,
+                usage: None,
+                picture: None,
+                occurs: None,
+                dimensions: Vec::new(),
+                variable_offset: false,
+                redefines: None,
+                sign: None,
+                synchronized: None,
+                keys: Vec::new(),
+                indexed_by: Vec::new(),
+                conditions: Vec::new(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Renders a built layout in the chosen format.
+pub fn render_layout(layout: &RecordLayout, options: &LayoutOptions) -> Result<String> {
+    match options.format {
+        OutputFormat::Listing => Ok(render_listing(layout, options)),
+        OutputFormat::Json => export::to_json(layout),
+        OutputFormat::Yaml => export::to_yaml(layout),
+        OutputFormat::Csv => Ok(export::to_delimited(layout, options.delimiter.unwrap_or(','), options)),
+        OutputFormat::Tsv => Ok(export::to_delimited(layout, options.delimiter.unwrap_or('\t'), options)),
+        OutputFormat::Markdown => Ok(markup::to_markdown(layout, options)),
+        OutputFormat::Html => Ok(markup::to_html(layout, options)),
+        OutputFormat::GnuCobol => render_gnucobol(layout),
+    }
+}
+
+/// Renders `layout` as the `.RL` listing. Each record gets a section of its
+/// own, and several sections end with a summary of the record lengths.
+pub fn render_listing(layout: &RecordLayout, options: &LayoutOptions) -> String {
     let mut output = String::new();
-    
-    // Header
-    output.push_str("Data Name                     Format         Type           N-Len  Pos  F-Len\n");
-    output.push_str("----------------------------- -------------- -------------- ----- ----- -----\n");
-    
-    let mut position = 1usize;
-    let mut total_length = 0usize;
-    
-    for field in &structure.root_fields {
-        // First pass to calculate total
-        let start_pos = position;
-        process_field(field, &mut String::new(), &mut position, &mut total_length, 0)?;
-        let calculated_total = position - start_pos;
-        
-        // Reset position and generate actual output
-        position = start_pos;
-        
-        // Generate output with correct N-Len for level 01
-        if field.level == 1 {
-            // Add the level 01 field with N-Len
-            let field_name = truncate_string(&field.name, 29);
+
+    for (index, record) in layout.records.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        render_section(record, &mut output, options);
+    }
+
+    if layout.records.len() > 1 {
+        output.push_str("\nRecord Summary\n");
+        output.push_str("Data Name                     Level  Length\n");
+        output.push_str("----------------------------- ----- ------\n");
+        for record in &layout.records {
+            let note = match &record.redefines {
+                Some(target) => format!(" REDEFINES {}", target),
+                None => String::new(),
+            };
             output.push_str(&format!(
-                "{:<29} {:<14} {:<14} {:5} {:5}      \n",
-                field_name, "", "", calculated_total, position
+                "{:<29} {:>5} {:6}{}\n",
+                truncate_string(&record.name, 29),
+                format!("{:02}", record.level),
+                record.length,
+                note
             ));
         }
-        
-        // Process children
-        for child in &field.children {
-            process_field(child, &mut output, &mut position, &mut total_length, 1)?;
+
+        // Every record of a file is a view of the same record area
+        for file in &layout.files {
+            let records: Vec<usize> = layout
+                .records
+                .iter()
+                .filter(|record| record.level == 1 && record.file.as_ref() == Some(&file.name))
+                .map(|record| record.length)
+                .collect();
+            if records.len() > 1 {
+                let longest = records.iter().max().unwrap_or(&0);
+                output.push_str(&format!(
+                    "* The {} records of {} share one record area of {} bytes\n",
+                    records.len(),
+                    file.name,
+                    longest
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+/// Writes one record's listing with its own heading and totals.
+fn render_section(record: &LayoutRecord, output: &mut String, options: &LayoutOptions) {
+    // Header
+    output.push_str("Data Name                     Format         Type           N-Len  Pos  F-Len\n");
+    output.push_str("----------------------------- -------------- -------------- ----- ----- -----\n");
+
+    let positions = listing_positions(&record.entries);
+    for (entry, &position) in record.entries.iter().zip(&positions) {
+        match entry.kind {
+            EntryKind::SlackBytes => output.push_str(&format!(
+                "{:<29} {:<14} {:<14} {} {:5} {:5}\n",
+                "SLACK-BYTES", "", "", "     ", position, entry.total_length()
+            )),
+            EntryKind::Renames => output.push_str(&format!(
+                "{:<29} {:<14} {:<14}       {:5} {:5}\n",
+                truncate_string(&entry.name, 29),
+                "RENAMES",
+                "",
+                position,
+                entry.storage_length
+            )),
+            EntryKind::Item if options.hide_filler && entry.filler => {}
+            // The level 01 line carries the record length in N-Len
+            EntryKind::Item if entry.level == 1 && entry.group => {
+                output.push_str(&format!(
+                    "{:<29} {:<14} {:<14} {:5} {:5}      \n",
+                    truncate_string(&entry.name, 29),
+                    "",
+                    "",
+                    entry.storage_length,
+                    position
+                ));
+                push_conditions(output, entry, options);
+            }
+            EntryKind::Item => {
+                // Flag positions that shift with an earlier OCCURS DEPENDING ON
+                let pos_str = if entry.variable_offset {
+                    format!("{:4}*", position)
+                } else {
+                    format!("{:5}", position)
+                };
+                let length_str = if entry.group || entry.storage_length == 0 {
+                    "     ".to_string()
+                } else {
+                    format!("{:5}", entry.total_length())
+                };
+                output.push_str(&format!(
+                    "{:<29} {:<14} {:<14} {} {} {}\n",
+                    truncate_string(&entry.name, 29),
+                    truncate_string(&format_column(entry), 14),
+                    truncate_string(&type_column(entry), 14),
+                    "     ",
+                    pos_str,
+                    length_str
+                ));
+                push_table_phrases(output, entry);
+                push_conditions(output, entry, options);
+            }
         }
-        
-        total_length = calculated_total;
     }
-    
+
     // Footer with total
     output.push_str("                                                                        -----\n");
-    output.push_str(&format!("                                        Total            {:6}\n", total_length));
-    
+    output.push_str(&format!("                                        Total            {:6}\n", record.length));
+    if let Some(min_length) = record.min_length {
+        output.push_str(&format!("                                        Minimum          {:6}\n", min_length));
+        output.push_str(&format!("                                        Maximum          {:6}\n", record.length));
+        output.push_str("* Pos varies with OCCURS DEPENDING ON\n");
+    }
+}
+
+/// Renders `layout` byte for byte as `mkrl.sh` and RL.CBL write the `.RL`:
+/// one listing for every record, groups with their length in N-Len, unnamed
+/// items named `fn001`, `fn002`, ... as DS-PP.CBL names them, and no trailing
+/// spaces. Inputs the original refuses are refused with its messages.
+pub fn render_gnucobol(layout: &RecordLayout) -> Result<String> {
+    let entries = || layout.records.iter().flat_map(|record| &record.entries);
+    let variable = layout
+        .files
+        .iter()
+        .filter_map(|file| file.record_contains.as_ref())
+        .any(|size| size.min.is_some() || size.depending_on.is_some())
+        || entries().any(|entry| entry.occurs.as_ref().is_some_and(|occurs| occurs.min.is_some()));
+    if variable {
+        bail!("variable length files not implemented");
+    }
+    if layout.records.iter().any(|record| record.redefines.is_some()) || entries().any(|entry| entry.redefines.is_some()) {
+        bail!("redefines not implemented");
+    }
+
+    let mut output = String::new();
+    output.push_str("Data Name                     Format         Type           N-Len  Pos  F-Len\n");
+    output.push_str("----------------------------- -------------- -------------- ----- ----- -----\n");
+
+    // Only elementary items move the position on, by every occurrence of them
+    let mut position = 1;
+    let mut unnamed = 0;
+    for entry in entries().filter(|entry| entry.kind == EntryKind::Item) {
+        let name = if entry.filler {
+            unnamed += 1;
+            format!("fn{:03}", unnamed)
+        } else {
+            entry.name.clone()
+        };
+        let (n_len, f_len) = match (&entry.occurs, entry.group) {
+            (Some(_), true) => (0, 0),
+            (Some(_), false) => bail!("{} has OCCURS but no subordinate items, which MKRL cannot lay out", name),
+            (None, true) => (entry.storage_length, 0),
+            (None, false) => (0, entry.total_length()),
+        };
+        output.push_str(&rl_record(&name, &format_column(entry), &type_column(entry), n_len, position, f_len));
+        position += f_len;
+    }
+
+    output.push_str("                                                                        -----\n");
+    output.push_str(&rl_record("", "          Total", "", 0, 0, position - 1));
     Ok(output)
 }
 
-fn process_field(
-    field: &CobolField,
-    output: &mut String,
-    position: &mut usize,
-    total_length: &mut usize,
-    _depth: usize,
-) -> Result<()> {
-    process_field_with_multiplier(field, output, position, total_length, _depth, 1)
-}
-
-fn process_field_with_multiplier(
-    field: &CobolField,
-    output: &mut String,
-    position: &mut usize,
-    total_length: &mut usize,
-    _depth: usize,
-    inherited_multiplier: usize,
-) -> Result<()> {
-    let field_name = truncate_string(&field.name, 29);
-    let format = get_format_string(field);
-    let data_type = field.data_type.as_deref().unwrap_or("").to_string();
-    
-    let (field_length, displayed_length) = calculate_field_length(field)?;
-    
-    // Calculate effective multiplier from parent OCCURS
-    let effective_multiplier = if let Some(occurs) = field.occurs {
-        inherited_multiplier * (occurs as usize)
-    } else {
-        inherited_multiplier
-    };
-    
-    // For group items (fields with children), show total length in N-Len column
-    let current_pos = *position;
-    
-    // Format the line
-    let pos_str = if field.children.is_empty() {
-        format!("{:5}", current_pos)
-    } else if field.level == 1 {
-        format!("{:5}", current_pos) 
-    } else {
-        // For group fields with OCCURS, show the starting position
-        format!("{:5}", current_pos)
-    };
-    
-    let length_str = if field.children.is_empty() && displayed_length > 0 {
-        let effective_length = displayed_length * effective_multiplier;
-        format!("{:5}", effective_length)
-    } else {
-        "     ".to_string()
-    };
-    
-    let total_len_str = if field.level == 1 {
-        "     ".to_string() // Will be filled in at the end based on total position advancement
-    } else {
-        "     ".to_string()
+/// One line of RL.CBL's `Rl-Rec`. Its numbers are `zzzzz` pictures, so zero
+/// shows as blanks, and a line sequential file drops the trailing spaces.
+fn rl_record(name: &str, format: &str, data_type: &str, n_len: usize, pos: usize, f_len: usize) -> String {
+    let number = |value: usize, width: usize| match value % 10usize.pow(width as u32) {
+        0 => " ".repeat(width),
+        digits => format!("{:>width$}", digits, width = width),
     };
-    
-    output.push_str(&format!(
-        "{:<29} {:<14} {:<14} {} {} {}\n",
-        field_name,
-        truncate_string(&format, 14),
-        truncate_string(&data_type, 14),
-        total_len_str,
-        pos_str,
-        length_str
-    ));
-    
-    if field.children.is_empty() {
-        // Leaf field - advance position 
-        *position += displayed_length * effective_multiplier;
-        if field.level == 1 {
-            *total_length = field_length * effective_multiplier;
+    let line = format!(
+        "{:<30}{:<15}{:<15}{}{}{}",
+        truncate_string(name, 30),
+        truncate_string(format, 15),
+        truncate_string(data_type, 15),
+        number(n_len, 5),
+        number(pos, 6),
+        number(f_len, 6)
+    );
+    format!("{}\n", line.trim_end())
+}
+
+/// The Pos the listing shows for each entry. MKRL multiplies a table's
+/// OCCURS down to its items, so an item's F-Len covers every occurrence and
+/// the items after it within the table are listed past all of them.
+fn listing_positions(entries: &[LayoutEntry]) -> Vec<usize> {
+    let mut positions: Vec<usize> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let position = match entry.parent {
+            Some(parent) => {
+                let group = &entries[parent];
+                let multiplier: usize = group.dimensions.iter().map(|d| d.max as usize).product();
+                positions[parent] + (entry.offset - group.offset) * multiplier
+            }
+            None => entry.offset + 1,
+        };
+        positions.push(position);
+    }
+    positions
+}
+
+/// Lists a table's KEY and INDEXED BY phrases beneath it, so the layout shows
+/// what a SEARCH of the table can use.
+fn push_table_phrases(output: &mut String, entry: &LayoutEntry) {
+    let mut orders: Vec<(bool, Vec<&str>)> = Vec::new();
+    for key in &entry.keys {
+        match orders.last_mut() {
+            Some((ascending, names)) if *ascending == key.ascending => names.push(&key.name),
+            _ => orders.push((key.ascending, vec![&key.name])),
         }
-    } else {
-        // Group field - process children
-        let start_pos = *position;
-        for child in &field.children {
-            process_field_with_multiplier(child, output, position, total_length, _depth + 1, effective_multiplier)?;
+    }
+    for (ascending, names) in orders {
+        let phrase = if ascending { "  ASCENDING KEY" } else { "  DESCENDING KEY" };
+        output.push_str(&format!("{:<29} {}\n", phrase, names.join(", ")));
+    }
+    if !entry.indexed_by.is_empty() {
+        output.push_str(&format!("{:<29} {}\n", "  INDEXED BY", entry.indexed_by.join(", ")));
+    }
+}
+
+/// Lists an item's condition names beneath it when asked to. They take no
+/// storage, so they show their values in place of a position and length.
+fn push_conditions(output: &mut String, entry: &LayoutEntry, options: &LayoutOptions) {
+    if !options.show_conditions {
+        return;
+    }
+    for condition in &entry.conditions {
+        let values: Vec<String> = condition
+            .values
+            .iter()
+            .map(|value| match &value.thru {
+                Some(thru) => format!("{} THRU {}", value.from, thru),
+                None => value.from.clone(),
+            })
+            .collect();
+        output.push_str(&format!(
+            "{:<29} VALUE {}\n",
+            truncate_string(&format!("  {}", condition.name), 29),
+            values.join(", ")
+        ));
+    }
+}
+/// Lists every record in `layout` with where it is described and its
+/// length, so records can be picked out of a whole program.
+pub fn list_records(layout: &RecordLayout) -> String {
+    let mut output = String::new();
+    output.push_str("Record                        Level Section          File              Line Length\n");
+    output.push_str("----------------------------- ----- ---------------- ---------------- ----- ------\n");
+    for record in &layout.records {
+        output.push_str(&format!(
+            "{:<29} {:>5} {:<16} {:<16} {:5} {:6}\n",
+            truncate_string(&record.name, 29),
+            format!("{:02}", record.level),
+            record.section.map(|s| s.to_string()).unwrap_or_default(),
+            truncate_string(record.file.as_deref().unwrap_or(""), 16),
+            record.line_number,
+            record.length
+        ));
+    }
+    output
+}
+
+/// Compares the records under each FD or SD with the sizes its RECORD clause
+/// declares, so a copybook that has drifted from the file is caught before
+/// the file is misread.
+pub fn check_record_contains(layout: &RecordLayout, main_file: Option<&str>) -> Vec<Diagnostic> {
+    let mut diagnostics = Diagnostics::new(false);
+    for file in &layout.files {
+        let Some(declared) = &file.record_contains else {
+            continue;
+        };
+        let mut shortest: Option<(&str, usize)> = None;
+        let mut longest: Option<(&str, usize)> = None;
+        for record in layout.records.iter().filter(|record| record.file.as_ref() == Some(&file.name)) {
+            let max = record.length;
+            let min = record.min_length.unwrap_or(max);
+            if shortest.is_none_or(|(_, length)| min < length) {
+                shortest = Some((&record.name, min));
+            }
+            if longest.is_none_or(|(_, length)| max > length) {
+                longest = Some((&record.name, max));
+            }
         }
-        
-        // For level 01 groups, set total length based on position advancement
-        if field.level == 1 {
-            *total_length = *position - start_pos;
+
+        // A single size must be met exactly; a range only bounds the records
+        let range = declared.min.is_some() || declared.varying;
+        let location = file.source_file.as_deref().or(main_file);
+        if let (Some(max), Some((record, length))) = (declared.max, longest) {
+            if (range && length > max) || (!range && length != max) {
+                let bound = if range { "at most " } else { "" };
+                diagnostics.warning(
+                    location,
+                    file.line_number,
+                    None,
+                    format!(
+                        "{} declares records of {}{} characters, but the longest, {}, is {} bytes",
+                        file.name, bound, max, record, length
+                    ),
+                );
+            }
+        }
+        if let (Some(min), Some((record, length))) = (declared.min, shortest) {
+            if length < min {
+                diagnostics.warning(
+                    location,
+                    file.line_number,
+                    None,
+                    format!(
+                        "{} declares records of at least {} characters, but the shortest, {}, is {} bytes",
+                        file.name, min, record, length
+                    ),
+                );
+            }
         }
     }
-    
-    Ok(())
+    diagnostics.into_sorted()
 }
 
-fn get_format_string(field: &CobolField) -> String {
-    if let Some(occurs) = field.occurs {
-        format!("OCCURS({})", occurs)
-    } else if let Some(picture) = &field.picture {
-        picture.clone()
+/// Splits the root items into records: each 01 and 77 starts one, and items
+/// of other levels without an 01 above them run together as a fragment.
+fn record_sections(roots: &[CobolField]) -> Vec<&[CobolField]> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    for (index, field) in roots.iter().enumerate() {
+        let starts_record = matches!(field.level, 1 | 77);
+        let previous_is_record = index > 0 && matches!(roots[index - 1].level, 1 | 77);
+        if index > start && (starts_record || previous_is_record) {
+            sections.push(&roots[start..index]);
+            start = index;
+        }
+    }
+    if start < roots.len() {
+        sections.push(&roots[start..]);
+    }
+    sections
+}
+
+/// Finds where the item named in `field`'s REDEFINES clause starts among the
+/// siblings laid out before it.
+fn redefined_start(placed: &[(&str, usize)], field: &CobolField) -> Result<Option<usize>> {
+    let Some(target) = &field.redefines else {
+        return Ok(None);
+    };
+
+    match placed.iter().rev().find(|(name, _)| name.eq_ignore_ascii_case(target)) {
+        Some((_, start)) => Ok(Some(*start)),
+        None => bail!(
+            "{} redefines {}, which is not a preceding item at the same level",
+            field.name,
+            target
+        ),
+    }
+}
+
+/// What the Format column shows: a table's OCCURS, or an item's PICTURE.
+fn format_column(entry: &LayoutEntry) -> String {
+    match (&entry.occurs, &entry.picture) {
+        (Some(Occurs { min: Some(min), max, .. }), _) => format!("OCCURS({}:{})", min, max),
+        (Some(occurs), _) => format!("OCCURS({})", occurs.max),
+        (None, Some(picture)) => picture.text.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// What the Type column shows: the usage and whether it is SYNCHRONIZED,
+/// where a SIGN clause puts the sign, or for a variable-length table the name
+/// of its DEPENDING ON object.
+fn type_column(entry: &LayoutEntry) -> String {
+    if let Some(sign) = entry.sign {
+        let position = match sign.position {
+            SignPosition::Leading => "LEADING",
+            SignPosition::Trailing => "TRAILING",
+        };
+        let separate = if sign.separate { " SEP" } else { "" };
+        // DISPLAY is the only usage a SIGN clause allows, so it goes without saying
+        return match entry.usage.as_deref() {
+            Some(usage) if usage != "DISPLAY" => format!("{} {}{}", usage, position, separate),
+            _ => format!("{}{}", position, separate),
+        };
+    }
+
+    let depending_on = entry.occurs.as_ref().and_then(|o| o.depending_on.as_deref());
+    let data_type = entry.usage.as_deref().or(depending_on).unwrap_or("");
+    if entry.synchronized.is_some() {
+        format!("{} SYNC", data_type).trim_start().to_string()
     } else {
-        String::new()
+        data_type.to_string()
     }
 }
 
-fn calculate_field_length(field: &CobolField) -> Result<(usize, usize)> {
-    if !field.children.is_empty() {
-        // Group field - calculate based on children (without OCCURS multiplication)
-        let mut total = 0;
-        
-        for child in &field.children {
-            let (_, child_displayed) = calculate_field_length(child)?;
-            total += child_displayed;
-        }
-        
-        Ok((total, total))
-    } else if let Some(picture) = &field.picture {
-        // Leaf field with picture clause (without OCCURS multiplication)
-        let actual_length = if let Some(comp_type) = &field.data_type {
+/// The boundary a SYNCHRONIZED elementary item of `length` bytes is aligned
+/// on; 1 for anything that is not aligned.
+fn sync_boundary(field: &CobolField, length: usize, policy: AlignmentPolicy) -> usize {
+    if field.synchronized.is_none() || !field.children.is_empty() {
+        return 1;
+    }
+    // Only binary, floating-point, pointer and index items have a natural boundary
+    let aligned = matches!(
+        field.data_type.as_deref(),
+        Some("COMP" | "COMP-1" | "COMP-2" | "COMP-4" | "COMP-5" | "BINARY" | "POINTER" | "INDEX")
+    );
+    match policy {
+        _ if !aligned || length == 0 => 1,
+        AlignmentPolicy::Natural => length.min(8),
+        AlignmentPolicy::Word => length.min(4),
+        AlignmentPolicy::None => 1,
+    }
+}
+
+/// Bytes one occurrence of the elementary item `field` takes.
+fn calculate_field_length(field: &CobolField) -> Result<usize> {
+    if let Some(picture) = &field.picture {
+        let length = if let Some(comp_type) = &field.data_type {
             calculate_comp_length(picture, comp_type)?
         } else {
             calculate_picture_length(picture)?
         };
-        
-        Ok((actual_length, actual_length))
+        Ok(length + separate_sign_length(field, picture)?)
     } else {
-        // Group field without picture (like OCCURS without PIC)
-        Ok((0, 0))
+        // COMP-1, COMP-2, POINTER and INDEX items take no PICTURE
+        Ok(field.data_type.as_deref().and_then(fixed_usage_length).unwrap_or(0))
     }
 }
 
+/// The extra character a SIGN ... SEPARATE clause adds to a signed DISPLAY item.
+fn separate_sign_length(field: &CobolField, picture: &str) -> Result<usize> {
+    let display = matches!(field.data_type.as_deref(), None | Some("DISPLAY"));
+    let separate = field.sign.is_some_and(|sign| sign.separate);
+    Ok(usize::from(display && separate && parse_picture(picture)?.signed))
+}
+
 fn calculate_picture_length(picture: &str) -> Result<usize> {
-    // Handle common COBOL picture patterns
-    if picture == "9" {
-        return Ok(1);
-    }
-    
-    // Handle s9(n)v9(n) format first (signed decimal with implied decimal point)
-    if let Some(captures) = regex::Regex::new(r"s?9\((\d+)\)(?:v9\((\d+)\))?")?.captures(picture) {
-        let int_digits: usize = captures.get(1).unwrap().as_str().parse()?;
-        let dec_digits: usize = captures.get(2).map(|m| m.as_str().parse().unwrap_or(0)).unwrap_or(0);
-        return Ok(int_digits + dec_digits);
-    }
-    
-    // Handle 9(n) format
-    if let Some(captures) = regex::Regex::new(r"^9\((\d+)\)$")?.captures(picture) {
-        let count: usize = captures.get(1).unwrap().as_str().parse()?;
-        return Ok(count);
-    }
-    
-    // Handle x(n) format (character)
-    if let Some(captures) = regex::Regex::new(r"x\((\d+)\)")?.captures(picture) {
-        let count: usize = captures.get(1).unwrap().as_str().parse()?;
-        return Ok(count);
-    }
-    
-    // Handle zzzzz9 format (zero suppression)
-    if picture == "zzzzz9" {
-        return Ok(6);
-    }
-    
-    // Default case - count characters
-    Ok(picture.len())
+    Ok(parse_picture(picture)?.display_size)
 }
 
 fn calculate_comp_length(picture: &str, comp_type: &str) -> Result<usize> {
+    if let Some(length) = fixed_usage_length(comp_type) {
+        return Ok(length);
+    }
+
     match comp_type {
-        "COMP-3" => {
-            // COMP-3 (packed decimal): (digits + 1) / 2 rounded up
-            let digits = if let Some(captures) = regex::Regex::new(r"s?9\((\d+)\)(?:v9\((\d+)\))?")?.captures(picture) {
-                let int_digits: usize = captures.get(1).unwrap().as_str().parse()?;
-                let dec_digits: usize = captures.get(2).map(|m| m.as_str().parse().unwrap_or(0)).unwrap_or(0);
-                int_digits + dec_digits
-            } else if picture == "9" {
-                1
-            } else {
-                calculate_picture_length(picture)?
-            };
-            Ok((digits + 1 + 1) / 2) // Add 1 for rounding up
+        "COMP-3" | "PACKED-DECIMAL" => {
+            // COMP-3 (packed decimal): one nibble per digit plus the sign nibble
+            let digits = parse_picture(picture)?.digits as usize;
+            Ok((digits + 1).div_ceil(2))
+        }
+        "COMP" | "COMP-4" | "COMP-5" | "BINARY" => {
+            // Binary formats - halfword, fullword or doubleword by digit count
+            Ok(binary_length(parse_picture(picture)?.digits))
         }
-        "COMP" | "COMP-1" | "COMP-2" => {
-            // Binary formats - typically 4 bytes for most integers
-            Ok(4)
+        "COMP-X" => {
+            // Micro Focus COMP-X - the fewest bytes that hold the largest value
+            let pic = parse_picture(picture)?;
+            if pic.category != PictureCategory::Numeric {
+                return Ok(pic.display_size);
+            }
+            let largest = 10u128.saturating_pow(pic.digits) - 1;
+            let value_bits = if pic.signed { 7 } else { 8 };
+            Ok((1..16).find(|bytes| largest >> (bytes * 8 - 8 + value_bits) == 0).unwrap_or(16) as usize)
         }
         _ => {
             // Unknown COMP type, use picture length
@@ -230,6 +1011,25 @@ fn calculate_comp_length(picture: &str, comp_type: &str) -> Result<usize> {
     }
 }
 
+/// Storage for usages whose size does not depend on a PICTURE.
+fn fixed_usage_length(usage: &str) -> Option<usize> {
+    match usage {
+        "COMP-1" | "INDEX" => Some(4),
+        // POINTER is sized for 64-bit GnuCOBOL, matching the original MKRL toolchain
+        "COMP-2" | "POINTER" => Some(8),
+        _ => None,
+    }
+}
+
+fn binary_length(digits: u32) -> usize {
+    match digits {
+        0..=4 => 2,
+        5..=9 => 4,
+        10..=18 => 8,
+        _ => 16,
+    }
+}
+
 fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -243,19 +1043,36 @@ mod tests {
     use super::*;
     use crate::cobol_parser::CobolField;
 
+    /// Lays out every record in `structure` and renders it in the chosen format.
+    fn generate_layout(structure: &CobolStructure, options: &LayoutOptions) -> Result<String> {
+        render_layout(&build_layout(structure, options)?, options)
+    }
+
     #[test]
     fn test_calculate_picture_length() {
         assert_eq!(calculate_picture_length("9(8)").unwrap(), 8);
         assert_eq!(calculate_picture_length("x(20)").unwrap(), 20);
         assert_eq!(calculate_picture_length("zzzzz9").unwrap(), 6);
         assert_eq!(calculate_picture_length("s9(9)v9(2)").unwrap(), 11);
+        assert_eq!(calculate_picture_length("s99v9(3)").unwrap(), 5);
+        assert_eq!(calculate_picture_length("zz,zz9.99-").unwrap(), 10);
     }
 
     #[test]  
     fn test_calculate_comp_length() {
         assert_eq!(calculate_comp_length("9(8)", "COMP-3").unwrap(), 5);
         assert_eq!(calculate_comp_length("s9(9)v9(2)", "COMP-3").unwrap(), 6);
-        assert_eq!(calculate_comp_length("9(4)", "COMP").unwrap(), 4);
+        assert_eq!(calculate_comp_length("s99v9(3)", "COMP-3").unwrap(), 3);
+        assert_eq!(calculate_comp_length("9(4)", "COMP").unwrap(), 2);
+        assert_eq!(calculate_comp_length("s9(9)", "COMP-4").unwrap(), 4);
+        assert_eq!(calculate_comp_length("9(18)", "COMP").unwrap(), 8);
+        assert_eq!(calculate_comp_length("s9(5)", "BINARY").unwrap(), 4);
+        assert_eq!(calculate_comp_length("s9(7)v99", "PACKED-DECIMAL").unwrap(), 5);
+        assert_eq!(calculate_comp_length("9(2)", "COMP-X").unwrap(), 1);
+        assert_eq!(calculate_comp_length("9(3)", "COMP-X").unwrap(), 2);
+        assert_eq!(calculate_comp_length("x(3)", "COMP-X").unwrap(), 3);
+        assert_eq!(calculate_comp_length("", "COMP-1").unwrap(), 4);
+        assert_eq!(calculate_comp_length("", "COMP-2").unwrap(), 8);
     }
 
     #[test]
@@ -265,13 +1082,542 @@ mod tests {
             name: "test-field".to_string(),
             picture: Some("9(8)".to_string()),
             data_type: Some("COMP-3".to_string()),
-            occurs: None,
-            children: Vec::new(),
             line_number: 1,
+            ..Default::default()
         };
         
-        let (total, displayed) = calculate_field_length(&field).unwrap();
-        assert_eq!(total, 5); // COMP-3 of 9(8) is 5 bytes
-        assert_eq!(displayed, 5);
+        assert_eq!(calculate_field_length(&field).unwrap(), 5); // COMP-3 of 9(8) is 5 bytes
+    }
+
+    #[test]
+    fn test_redefines_shares_position() {
+        let content = "       01  Rec.
+           02  Rec-Date        pic 9(8).
+           02  Rec-Date-Parts redefines Rec-Date.
+               03  Rec-Year    pic 9(4).
+               03  Rec-Mmdd    pic 9(4).
+           02  Rec-Amount      pic x(12).
+           02  Rec-Amount-Pk redefines Rec-Amount pic s9(9)v9(2) COMP-3.
+           02  Rec-Tail        pic x.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        let pos_of = |name: &str| -> usize {
+            let line = layout.lines().find(|l| l.starts_with(&format!("{} ", name))).unwrap();
+            line[66..71].trim().parse().unwrap()
+        };
+
+        assert_eq!(pos_of("Rec-Date-Parts"), 1);
+        assert_eq!(pos_of("Rec-Year"), 1);
+        assert_eq!(pos_of("Rec-Mmdd"), 5);
+        assert_eq!(pos_of("Rec-Amount-Pk"), 9);
+        assert_eq!(pos_of("Rec-Tail"), 21);
+        assert!(layout.lines().nth(2).unwrap().contains("   21     1"));
+    }
+
+    #[test]
+    fn test_redefines_group_length_takes_longest() {
+        let content = "       01  Rec.
+           02  Rec-Short       pic x(4).
+           02  Rec-Long redefines Rec-Short pic x(10).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert_eq!(layout.records[0].length, 10);
+    }
+
+    #[test]
+    fn test_occurs_depending_on_lengths() {
+        let content = "       01  Rec.
+           02  Rec-Count       pic 99.
+           02  Rec-Entry occurs 1 to 10 times depending on Rec-Count.
+               03  Rec-Code    pic x(3).
+           02  Rec-Tail        pic x(4).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+
+        assert!(layout.contains("Rec-Entry                     OCCURS(1:10)   Rec-Count"));
+        assert!(layout.contains("Rec-Code                      x(3)                                    3    30"));
+        assert!(layout.contains("Rec-Tail                      x(4)                                  33*     4"));
+        assert!(layout.contains("Minimum               9"));
+        assert!(layout.contains("Maximum              36"));
+    }
+
+    #[test]
+    fn test_minimum_length_counts_slack_bytes() {
+        let content = "       01  Rec.
+           02  Rec-Count       pic 99.
+           02  Rec-Flag        pic x.
+           02  Rec-Entry occurs 2 to 4 times depending on Rec-Count.
+               03  Rec-Code    pic x.
+               03  Rec-Num     pic s9(4) comp sync.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let record = &build_layout(&structure, &LayoutOptions::default()).unwrap().records[0];
+        let entry = &record.entries[3];
+        assert_eq!(entry.name, "Rec-Entry");
+        assert_eq!(record.length, entry.offset + 4 * entry.storage_length);
+        assert_eq!(record.min_length, Some(entry.offset + 2 * entry.storage_length));
+    }
+
+    #[test]
+    fn test_nested_tables_shrink_together() {
+        let content = "       01  Rec.
+           02  Rec-Outer occurs 1 to 3 times depending on Rec-Count.
+               03  Rec-Inner-Count pic 9.
+               03  Rec-Inner   pic x(2) occurs 1 to 5 times depending on Rec-Inner-Count.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let record = &build_layout(&structure, &LayoutOptions::default()).unwrap().records[0];
+        assert_eq!(record.length, 33);
+        assert_eq!(record.min_length, Some(3));
+    }
+
+    #[test]
+    fn test_usage_without_picture() {
+        let content = "       01  Rec.
+           02  Rec-Rate        COMP-2.
+           02  Rec-Ptr         POINTER.
+           02  Rec-Idx         INDEX.
+           02  Rec-Count       pic s9(4) BINARY.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let lengths: Vec<usize> = structure.root_fields[0]
+            .children
+            .iter()
+            .map(|f| calculate_field_length(f).unwrap())
+            .collect();
+        assert_eq!(lengths, vec![8, 8, 4, 2]);
+    }
+
+    #[test]
+    fn test_filler_takes_space_and_can_be_hidden() {
+        let content = "       01  Dtom-File-Record.
+           02  Om-Custid       pic x(6).
+           02                  pic x(180).
+           02  filler          pic x(4).
+           02  Om-Tail         pic x.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+
+        let header = "Data Name                     Format         Type           N-Len  Pos  F-Len
+----------------------------- -------------- -------------- ----- ----- -----
+Dtom-File-Record                                              191     1      \n";
+        let footer = "                                                                        -----
+                                        Total               191
+";
+
+        // Unnamed items and FILLER however it is spelled are listed as FILLER
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert_eq!(
+            layout,
+            format!(
+                "{}Om-Custid                     x(6)                                    1     6
+FILLER                        x(180)                                  7   180
+FILLER                        x(4)                                  187     4
+Om-Tail                       x                                     191     1
+{}",
+                header, footer
+            )
+        );
+
+        let hidden = generate_layout(&structure, &LayoutOptions { hide_filler: true, ..Default::default() }).unwrap();
+        assert_eq!(
+            hidden,
+            format!(
+                "{}Om-Custid                     x(6)                                    1     6
+Om-Tail                       x                                     191     1
+{}",
+                header, footer
+            )
+        );
+    }
+
+    #[test]
+    fn test_conditions_take_no_storage() {
+        let content = "       01  Rec.
+           02  Rec-Status      pic x.
+               88  Rec-Open            value 'O'.
+               88  Rec-Valid           values 'A' thru 'M', 'X'.
+           02  Rec-Tail        pic x(4).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert!(!layout.contains("Rec-Open"));
+        assert!(layout.contains("Rec-Tail                      x(4)                                    2     4"));
+        assert!(layout.contains("Total                 5"));
+
+        let options = LayoutOptions { show_conditions: true, ..Default::default() };
+        let layout = generate_layout(&structure, &options).unwrap();
+        let lines: Vec<&str> = layout.lines().collect();
+        assert_eq!(lines[4], "  Rec-Open                    VALUE 'O'");
+        assert_eq!(lines[5], "  Rec-Valid                   VALUE 'A' THRU 'M', 'X'");
+        assert!(lines[6].starts_with("Rec-Tail "));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_renames_span_without_storage() {
+        let content = "       01  Cust-Record.
+           05  Cust-Id         pic x(6).
+           05  Cust-Name.
+               10  Cust-First  pic x(10).
+               10  Cust-Last   pic x(15).
+           05  Cust-Zip        pic 9(5).
+       66  Cust-Key renames Cust-Id thru Cust-First.
+       66  Cust-Full-Name renames Cust-Name.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+
+        assert!(layout.contains("Cust-Key                      RENAMES                                 1    16"));
+        assert!(layout.contains("Cust-Full-Name                RENAMES                                 7    25"));
+        assert!(layout.contains("Total                36"));
+    }
+
+    #[test]
+    fn test_section_per_record() {
+        let content = "       fd  Tran-File.
+       01  Tran-Header.
+           05  Th-Type         pic x.
+           05  Th-Date         pic 9(8).
+       01  Tran-Detail.
+           05  Td-Type         pic x.
+           05  Td-Amount       pic s9(7)v99 comp-3.
+       01  Tran-Raw redefines Tran-Detail pic x(6).
+       77  Ws-Count            pic 9(4) comp.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+
+        assert_eq!(layout.matches("Data Name                     Format").count(), 4);
+        assert!(layout.contains("Td-Type                       x                                       1     1"));
+        assert!(layout.contains("Tran-Raw                      x(6)                                    1     6"));
+        assert!(layout.contains("Ws-Count                      9(4)           COMP                     1     2"));
+        assert!(layout.contains("Total                 9"));
+        assert!(layout.contains("Total                 6"));
+        assert!(layout.ends_with(
+            "Record Summary
+Data Name                     Level  Length
+----------------------------- ----- ------
+Tran-Header                      01      9
+Tran-Detail                      01      6
+Tran-Raw                         01      6 REDEFINES Tran-Detail
+Ws-Count                         77      2
+* The 3 records of Tran-File share one record area of 9 bytes
+"
+        ));
+    }
+
+    #[test]
+    fn test_single_record_has_no_summary() {
+        let content = "       01  Rec.
+           02  Rec-Id          pic x(4).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert!(!layout.contains("Record Summary"));
+        assert!(layout.ends_with("Total                 4\n"));
+    }
+
+    #[test]
+    fn test_separate_sign_takes_a_byte() {
+        let content = "       01  Rec.
+           02  Rec-Lead        pic s9(5) sign leading separate.
+           02  Rec-Trail       pic s9(5) sign is trailing.
+           02  Rec-Tail        pic x.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+
+        assert!(layout.contains("Rec-Lead                      s9(5)          LEADING SEP              1     6"));
+        assert!(layout.contains("Rec-Trail                     s9(5)          TRAILING                 7     5"));
+        assert!(layout.contains("Rec-Tail                      x                                      12     1"));
+    }
+
+    #[test]
+    fn test_sync_inserts_slack_bytes() {
+        let content = "       01  Rec.
+           02  Rec-Flag        pic x.
+           02  Rec-Count       pic s9(9) comp sync.
+           02  Rec-Code        pic x(3).
+           02  Rec-Total       pic s9(18) comp-5 synchronized.
+           02  Rec-Short       pic s9(4) binary sync left.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        let lines: Vec<&str> = layout.lines().collect();
+        assert_eq!(lines[3], "Rec-Flag                      x                                       1     1");
+        assert_eq!(lines[4], "SLACK-BYTES                                                           2     3");
+        assert_eq!(lines[5], "Rec-Count                     s9(9)          COMP SYNC                5     4");
+        assert_eq!(lines[7], "SLACK-BYTES                                                          12     5");
+        assert_eq!(lines[8], "Rec-Total                     s9(18)         COMP-5 SYNC             17     8");
+        assert_eq!(lines[9], "Rec-Short                     s9(4)          BINARY SYNC             25     2");
+        assert!(layout.contains("Total                26"));
+
+        let word = LayoutOptions { alignment: AlignmentPolicy::Word, ..Default::default() };
+        let layout = generate_layout(&structure, &word).unwrap();
+        assert!(layout.contains("SLACK-BYTES                                                          12     1"));
+        assert!(layout.contains("Total                22"));
+
+        let none = LayoutOptions { alignment: AlignmentPolicy::None, ..Default::default() };
+        let layout = generate_layout(&structure, &none).unwrap();
+        assert!(!layout.contains("SLACK-BYTES"));
+        assert!(layout.contains("Total                18"));
+    }
+
+    #[test]
+    fn test_sync_pads_table_occurrences() {
+        let content = "       01  Rec.
+           05  Rec-Table occurs 3.
+               10  Rec-Count   pic s9(9) comp sync.
+               10  Rec-Flag    pic x.
+           05  Rec-Tail        pic x.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+
+        // Each occurrence is padded to 8 bytes so every Rec-Count is on a fullword
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        let entries = &layout.records[0].entries;
+        assert_eq!(entries[1].storage_length, 8);
+        assert_eq!((entries[4].kind, entries[4].offset, entries[4].storage_length), (EntryKind::SlackBytes, 5, 3));
+        assert_eq!(entries[4].parent, Some(1));
+        assert_eq!((entries[5].name.as_str(), entries[5].offset), ("Rec-Tail", 24));
+        assert_eq!(layout.records[0].length, 25);
+
+        let listing = render_listing(&layout, &LayoutOptions::default());
+        assert!(listing.contains("\nSLACK-BYTES                                                          16     9\n"));
+        assert!(listing.contains("\nRec-Tail                      x                                      25     1\n"));
+
+        let none = LayoutOptions { alignment: AlignmentPolicy::None, ..Default::default() };
+        assert_eq!(build_layout(&structure, &none).unwrap().records[0].length, 16);
+    }
+
+    #[test]
+    fn test_table_keys_and_indexes_are_listed() {
+        let content = "       01  Rate-Table.
+           05  Rate-Entry occurs 20 times
+                   ascending key is Rate-Code descending key Rate-Date
+                   indexed by Rate-Idx.
+               10  Rate-Code   pic x(3).
+               10  Rate-Date   pic 9(8).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = generate_layout(&structure, &LayoutOptions::default()).unwrap();
+        let lines: Vec<&str> = layout.lines().collect();
+
+        assert!(lines[3].starts_with("Rate-Entry                    OCCURS(20)"));
+        assert_eq!(lines[4], "  ASCENDING KEY               Rate-Code");
+        assert_eq!(lines[5], "  DESCENDING KEY              Rate-Date");
+        assert_eq!(lines[6], "  INDEXED BY                  Rate-Idx");
+        assert!(lines[7].starts_with("Rate-Code "));
+        assert!(layout.contains("Total               220"));
+    }
+
+    #[test]
+    fn test_layout_model_offsets() {
+        let content = "       01  Dinc-Rec.
+           02  Dinc-Date       pic zzzzz9.
+           02  Dinc-Bsta occurs 2 times.
+               03  Dinc-Class occurs 12 times.
+                   04  Dinc-Amount pic s9(9)v9(2) comp-3.
+                   04  Dinc-Flag   pic x.
+           02  Dinc-Tail       pic x(4).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        let record = &layout.records[0];
+        let entry = |name: &str| record.entries.iter().find(|e| e.name == name).unwrap();
+
+        assert_eq!(record.length, 178);
+        assert_eq!(record.min_length, None);
+
+        // Offsets are real: the second occurrence of Dinc-Class follows the first
+        let flag = entry("Dinc-Flag");
+        assert_eq!((flag.offset, flag.storage_length, flag.total_length()), (12, 1, 24));
+        assert_eq!(flag.path, vec!["Dinc-Rec", "Dinc-Bsta", "Dinc-Class", "Dinc-Flag"]);
+        assert_eq!(flag.dimensions.iter().map(|d| d.max).collect::<Vec<_>>(), vec![2, 12]);
+        assert_eq!(record.entries[flag.parent.unwrap()].name, "Dinc-Class");
+        assert_eq!(entry("Dinc-Class").storage_length, 7);
+        assert_eq!(entry("Dinc-Bsta").storage_length, 84);
+        assert_eq!(entry("Dinc-Tail").offset, 174);
+
+        let amount = entry("Dinc-Amount");
+        assert_eq!((amount.storage_length, amount.display_length), (6, Some(11)));
+        assert_eq!(amount.usage.as_deref(), Some("COMP-3"));
+        let picture = amount.picture.as_ref().unwrap();
+        assert_eq!((picture.digits, picture.scale, picture.signed), (11, 2, true));
+
+        // The listing multiplies the tables down to their items
+        let listing = render_listing(&layout, &LayoutOptions::default());
+        assert!(listing.contains("Dinc-Amount                   s9(9)v9(2)     COMP-3                   7   144"));
+        assert!(listing.contains("Dinc-Flag                     x                                     151    24"));
+        assert!(listing.contains("Dinc-Tail                     x(4)                                  175     4"));
+    }
+
+    #[test]
+    fn test_list_records() {
+        let content = "       data division.
+       file section.
+       fd  Rl-File.
+       01  Rl-File-Record.
+           02             pic x(81).
+       working-storage section.
+       77  Ws-Count        pic 9(4) comp.
+       procedure division.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let mut layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        let listing = list_records(&layout);
+
+        assert_eq!(
+            listing,
+            "Record                        Level Section          File              Line Length
+----------------------------- ----- ---------------- ---------------- ----- ------
+Rl-File-Record                   01 FILE             Rl-File              4     81
+Ws-Count                         77 WORKING-STORAGE                       7      2
+"
+        );
+
+        layout.retain_records(&["ws-count".to_string()]).unwrap();
+        assert_eq!(layout.records.len(), 1);
+        assert!(layout.retain_records(&["Missing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_record_contains_is_checked() {
+        let content = "       fd  Cust-File record contains 80 characters.
+       01  Cust-Rec.
+           05  Cust-Id         pic x(6).
+           05  Cust-Name       pic x(70).
+       fd  Hist-File record contains 20 to 50 characters.
+       01  Hist-Rec.
+           05  Hist-Count      pic 99.
+           05  Hist-Entry      pic x(8)
+                               occurs 1 to 7 depending on Hist-Count.
+       fd  Note-File record is varying from 50 to 90.
+       01  Note-Rec            pic x(90).
+       fd  Log-File record contains 10 to 60 characters.
+       01  Log-Rec.
+           05  Log-Count       pic 99.
+           05  Log-Entry       pic x(8)
+                               occurs 1 to 7 depending on Log-Count.
+       fd  Memo-File record is varying in size to 40 characters.
+       01  Memo-Rec            pic x(30).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        let warnings: Vec<String> = check_record_contains(&layout, Some("CUST.FD"))
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+
+        // Records anywhere within a declared range are fine
+        assert_eq!(
+            warnings,
+            vec![
+                "CUST.FD:1: warning: Cust-File declares records of 80 characters, but the longest, Cust-Rec, is 76 bytes",
+                "CUST.FD:5: warning: Hist-File declares records of at most 50 characters, but the longest, Hist-Rec, is 58 bytes",
+                "CUST.FD:5: warning: Hist-File declares records of at least 20 characters, but the shortest, Hist-Rec, is 10 bytes",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_entries_is_an_error() {
+        let content = "01 REC.\n   05 A PIC X(5).\n";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        assert!(!structure.diagnostics.is_empty());
+        let err = generate_layout(&structure, &LayoutOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no data description entries found (if the source starts in column 1, try --source-format free)"
+        );
+
+        let options = crate::cobol_parser::ParseOptions {
+            source_format: crate::source_format::SourceFormat::Free,
+            ..Default::default()
+        };
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &options).unwrap();
+        assert_eq!(build_layout(&structure, &LayoutOptions::default()).unwrap().records[0].length, 5);
+    }
+
+    /// Lays out one of the examples shipped with the COBOL original.
+    fn layout_example(file_name: &str, format: OutputFormat) -> Result<String> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("MKRL-0.7.1/examples").join(file_name);
+        let content = std::fs::read_to_string(&path)?;
+        let options = crate::cobol_parser::ParseOptions { source_path: Some(path), ..Default::default() };
+        let structure = crate::cobol_parser::parse_cobol_structure(&content, &options)?;
+        generate_layout(&structure, &LayoutOptions { format, ..Default::default() })
+    }
+
+    /// The `.RL` shipped with an example.
+    fn shipped_listing(file_name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("MKRL-0.7.1/examples")
+            .join(file_name)
+            .with_extension("RL");
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_examples_match_gnucobol_output() {
+        // Every shipped .RL was laid out by mkrl.sh and RL.CBL under GnuCOBOL
+        let examples = [
+            "BD.FD", "DARCM.FD", "DARSP.FD", "DINC.FD", "DTOD.CBL", "DTOH.CBL", "DTOM.FD", "SALES.FD", "SYSDATES.FD",
+            "TINMAST.FD",
+        ];
+        for file_name in examples {
+            let expected = shipped_listing(file_name);
+            // TINMAST.RL starts with a blank line, which RL.CBL cannot have
+            // written: the column header is the first thing it writes
+            let expected = expected.strip_prefix('\n').unwrap_or(&expected);
+            let actual = layout_example(file_name, OutputFormat::GnuCobol).unwrap();
+            assert_eq!(actual, expected, "{}", file_name);
+        }
+
+        // mkrl.sh refused CARLOCK and wrote why in place of a layout
+        let error = layout_example("CARLOCK.FD", OutputFormat::GnuCobol).unwrap_err();
+        let expected = shipped_listing("CARLOCK.FD");
+        assert_eq!(format!("ERROR: {}", error), expected.lines().next().unwrap());
+    }
+
+    #[test]
+    fn test_gnucobol_listing() {
+        let content = "       01  Sales-File-Record.
+           02  Sales-File-Key.
+               03  S-Product-ID  pic x(6).
+           02  S-Region occurs 2 times.
+               03  S-Amount      pic s9(5) COMP-3.
+               03                pic x(2).
+           02  Sales-Long-Name-For-The-Description pic x(30).
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert_eq!(
+            render_gnucobol(&layout).unwrap(),
+            "Data Name                     Format         Type           N-Len  Pos  F-Len
+----------------------------- -------------- -------------- ----- ----- -----
+Sales-File-Record                                              46     1
+Sales-File-Key                                                  6     1
+S-Product-ID                  x(6)                                    1     6
+S-Region                      OCCURS(2)                               7
+S-Amount                      s9(5)          COMP-3                   7     6
+fn001                         x(2)                                   13     4
+Sales-Long-Name-For-The-Descrix(30)                                  17    30
+                                                                        -----
+                                        Total                              46
+"
+        );
+
+        let content = "       01  Rec.
+           02  Rec-Code  pic x.
+           02  Rec-Num   redefines Rec-Code pic 9.
+";
+        let structure = crate::cobol_parser::parse_cobol_structure(content, &Default::default()).unwrap();
+        let layout = build_layout(&structure, &LayoutOptions::default()).unwrap();
+        assert_eq!(render_gnucobol(&layout).unwrap_err().to_string(), "redefines not implemented");
+    }
+}
@@ -0,0 +1,395 @@
+use crate::diagnostics::Diagnostics;
+
+/// Column 7 of fixed-format source, and where area B ends.
+const INDICATOR_COLUMN: usize = 7;
+const AREA_B_END: usize = 72;
+/// Width of the program text area, columns 8-72, which a continued literal fills.
+const TEXT_AREA_WIDTH: usize = AREA_B_END - INDICATOR_COLUMN;
+
+/// Words after which a line starting with a number continues the entry rather
+/// than starting a new one that lost its period.
+const OPERAND_KEYWORDS: &[&str] = &[
+    "OCCURS", "TO", "VALUE", "VALUES", "IS", "ARE", "THRU", "THROUGH", "FROM", "CONTAINS",
+];
+
+/// The reference format a COBOL source is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceFormat {
+    /// Columns 1-6 sequence area, column 7 indicator, program text in 8-72.
+    #[default]
+    Fixed,
+    /// GnuCOBOL free format: program text anywhere, `*>` starts a comment.
+    Free,
+}
+
+impl std::str::FromStr for SourceFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(SourceFormat::Fixed),
+            "free" => Ok(SourceFormat::Free),
+            _ => anyhow::bail!("unknown source format: {} (expected fixed or free)", s),
+        }
+    }
+}
+
+/// A line of program text with the indicator area and sequence areas removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    /// 1-based physical line number in the input.
+    pub number: usize,
+    /// The column 7 indicator, or a space for free format and normal lines.
+    pub indicator: char,
+    /// Column of the physical line where `text` starts.
+    pub column: usize,
+    pub text: String,
+}
+
+impl SourceLine {
+    pub fn is_continuation(&self) -> bool {
+        self.indicator == '-'
+    }
+}
+
+/// A sentence of source text, such as one data description entry, joined
+/// across lines up to its terminating period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceEntry {
+    /// Line number where the entry starts.
+    pub line_number: usize,
+    /// Column where the entry starts on that line.
+    pub column: usize,
+    /// Copybook the entry came from, or `None` for the main input.
+    pub file: Option<String>,
+    pub text: String,
+    /// Whether the entry ended with a period rather than running into the next one.
+    pub terminated: bool,
+}
+
+/// Reads COBOL source in the given starting format, dropping comment and
+/// debugging lines and honoring `>>SOURCE FORMAT` directives along the way.
+pub fn read_source(content: &str, format: SourceFormat) -> Vec<SourceLine> {
+    let mut format = format;
+    let mut lines = Vec::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let raw = expand_tabs(raw);
+        let chars: Vec<char> = raw.chars().collect();
+
+        // Directives may start anywhere after the sequence area
+        let directive_area: String = match format {
+            SourceFormat::Fixed => chars.iter().skip(INDICATOR_COLUMN - 1).collect(),
+            SourceFormat::Free => raw.clone(),
+        };
+        if let Some(directive) = directive_area.trim_start().strip_prefix(">>") {
+            if let Some(switched) = source_directive(directive) {
+                format = switched;
+            }
+            continue;
+        }
+
+        let (indicator, column, text) = match format {
+            SourceFormat::Fixed => {
+                let indicator = chars.get(INDICATOR_COLUMN - 1).copied().unwrap_or(' ');
+                let text: String = chars
+                    .iter()
+                    .take(AREA_B_END)
+                    .skip(INDICATOR_COLUMN)
+                    .collect();
+                (indicator, INDICATOR_COLUMN + 1, text)
+            }
+            SourceFormat::Free => {
+                if raw.trim_start().starts_with("*>") {
+                    continue;
+                }
+                (' ', 1, raw)
+            }
+        };
+
+        // Comment and debugging lines take no part, nor do `$` directive lines
+        match indicator {
+            '*' | '/' | 'D' | 'd' | '$' => continue,
+            _ => {}
+        }
+
+        lines.push(SourceLine {
+            number: index + 1,
+            indicator,
+            column,
+            text: text.trim_end().to_string(),
+        });
+    }
+
+    lines
+}
+
+/// Warns about lines whose column 7 holds something other than an indicator;
+/// their text is still read as program text.
+pub fn check_indicators(lines: &[SourceLine], file: Option<&str>, diagnostics: &mut Diagnostics) {
+    for line in lines.iter().filter(|line| !matches!(line.indicator, ' ' | '-')) {
+        diagnostics.warning(
+            file,
+            line.number,
+            Some(INDICATOR_COLUMN),
+            format!("'{}' in column 7 is not an indicator", line.indicator),
+        );
+    }
+}
+
+/// Joins source lines into period-terminated entries. Continuation lines extend
+/// an open literal from column 72 or glue a split word back together, and
+/// `*>` comments are dropped.
+pub fn join_entries(lines: &[SourceLine]) -> Vec<SourceEntry> {
+    let mut entries = Vec::new();
+    let mut current = SourceEntry {
+        line_number: 0,
+        column: 0,
+        file: None,
+        text: String::new(),
+        terminated: false,
+    };
+    let mut quote: Option<char> = None;
+    // Characters of the previous line's text area already consumed
+    let mut column = 0;
+
+    for line in lines {
+        let text: &str = if line.is_continuation() && !current.text.is_empty() {
+            match quote {
+                Some(q) => {
+                    // The open literal runs to column 72 and resumes after the next quote
+                    current.text.extend(std::iter::repeat_n(' ', TEXT_AREA_WIDTH.saturating_sub(column)));
+                    match line.text.find(q) {
+                        Some(i) => &line.text[i + q.len_utf8()..],
+                        None => line.text.trim_start(),
+                    }
+                }
+                None => line.text.trim_start(),
+            }
+        } else {
+            if !current.text.is_empty() {
+                if quote.is_none() && starts_new_entry(&current.text, &line.text) {
+                    entries.push(finish_entry(&mut current, false));
+                } else {
+                    current.text.push('\n');
+                }
+            }
+            &line.text
+        };
+
+        column = line.text[..line.text.len() - text.len()].chars().count();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            column += 1;
+            if quote.is_none() && c == '*' && chars.peek() == Some(&'>') {
+                break;
+            }
+            if current.text.trim().is_empty() {
+                if c.is_whitespace() {
+                    continue;
+                }
+                current.text.clear();
+                current.line_number = line.number;
+                current.column = line.column + column - 1;
+            }
+            current.text.push(c);
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c == '.' && chars.peek().is_none_or(|n| n.is_whitespace()) => {
+                    entries.push(finish_entry(&mut current, true));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if !current.text.trim().is_empty() {
+        entries.push(finish_entry(&mut current, false));
+    }
+
+    entries
+}
+
+fn finish_entry(current: &mut SourceEntry, terminated: bool) -> SourceEntry {
+    let text = std::mem::take(&mut current.text);
+    SourceEntry {
+        line_number: current.line_number,
+        column: current.column,
+        file: None,
+        text: text.split('\n').map(str::trim).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" "),
+        terminated,
+    }
+}
+
+/// Whether `line` opens a new level-numbered entry while `pending` is a data
+/// description entry whose terminating period is missing. A condition name's
+/// VALUE list may run over several lines, any of which can start with a small
+/// number, so an 88 entry is only ever ended by its period.
+fn starts_new_entry(pending: &str, line: &str) -> bool {
+    let is_level = |word: Option<&str>| {
+        word.is_some_and(|w| w.len() <= 2 && w.chars().all(|c| c.is_ascii_digit()))
+    };
+    let mut pending_words = pending.split_whitespace();
+    let pending_level = pending_words.next();
+    if !is_level(pending_level) || pending_level == Some("88") || !is_level(line.split_whitespace().next()) {
+        return false;
+    }
+    let last = pending_words.last().unwrap_or("").to_ascii_uppercase();
+    !OPERAND_KEYWORDS.contains(&last.as_str())
+}
+
+/// Returns the format selected by a `>>SOURCE [FORMAT] [IS] FIXED|FREE` directive.
+fn source_directive(directive: &str) -> Option<SourceFormat> {
+    let words: Vec<String> = directive
+        .split_whitespace()
+        .map(|w| w.to_ascii_uppercase())
+        .collect();
+    if words.first().map(String::as_str) != Some("SOURCE") {
+        return None;
+    }
+    words[1..]
+        .iter()
+        .filter(|w| *w != "FORMAT" && *w != "IS")
+        .find_map(|w| w.parse().ok())
+}
+
+/// Expands tabs to the next multiple of eight columns, as GnuCOBOL does.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = 8 - column % 8;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(sequence: &str, indicator: char, text: &str, tag: &str) -> String {
+        format!("{:<6}{}{:<65}{}\n", sequence, indicator, text, tag)
+    }
+
+    #[test]
+    fn test_fixed_format_areas() {
+        let content = [
+            card("000100", ' ', "01  Cust-Record.", "CUSTV2"),
+            card("000200", '*', "    comment line", ""),
+            card("000300", '/', "    page eject", ""),
+            card("000400", 'D', "    05  Debug-Only pic x.", ""),
+            card("000500", ' ', "    05  Cust-Id                pic x(6).", "CUSTV2"),
+        ]
+        .concat();
+        let lines = read_source(&content, SourceFormat::Fixed);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].number, 1);
+        assert_eq!(lines[0].text, "01  Cust-Record.");
+        assert_eq!(lines[1].number, 5);
+        assert_eq!(lines[1].text, "    05  Cust-Id                pic x(6).");
+    }
+
+    #[test]
+    fn test_continuation_indicator() {
+        let content = "       01  Msg pic x(20) value \"abc\n      -    \"def\".\n";
+        let lines = read_source(content, SourceFormat::Fixed);
+
+        assert!(!lines[0].is_continuation());
+        assert!(lines[1].is_continuation());
+    }
+
+    #[test]
+    fn test_entries_span_lines() {
+        let content = "       01  Ws-Record.
+           05  Ws-Amount
+               PIC S9(7)V99 COMP-3.  *> running total
+           05  Ws-Count pic 9(4). 05  Ws-Flag pic x.
+           05  Ws-Rows occurs
+               12 times pic x.
+           05  Ws-Missing-Period pic x
+           05  Ws-Last pic x.
+";
+        let entries = join_entries(&read_source(content, SourceFormat::Fixed));
+        let found: Vec<(usize, &str)> = entries.iter().map(|e| (e.line_number, e.text.as_str())).collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (1, "01  Ws-Record."),
+                (2, "05  Ws-Amount PIC S9(7)V99 COMP-3."),
+                (4, "05  Ws-Count pic 9(4)."),
+                (4, "05  Ws-Flag pic x."),
+                (5, "05  Ws-Rows occurs 12 times pic x."),
+                (7, "05  Ws-Missing-Period pic x"),
+                (8, "05  Ws-Last pic x."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_columns_and_periods() {
+        let content = "       01  Ws-Record.
+           05  Ws-Key pic x(4).   05  Ws-Flag pic x
+           05  Ws-Last pic x
+";
+        let entries = join_entries(&read_source(content, SourceFormat::Fixed));
+        let found: Vec<(usize, usize, bool)> = entries.iter().map(|e| (e.line_number, e.column, e.terminated)).collect();
+        assert_eq!(found, vec![(1, 8, true), (2, 12, true), (2, 35, false), (3, 12, false)]);
+
+        let entries = join_entries(&read_source("  01 Rec.\n", SourceFormat::Free));
+        assert_eq!(entries[0].column, 3);
+    }
+
+    #[test]
+    fn test_continued_literal() {
+        let content = [
+            card("", ' ', "    05  Ws-Msg pic x(40) value \"ABCDEFGHIJ", ""),
+            card("", '-', "    \"KLM\".", ""),
+        ]
+        .concat();
+        let entries = join_entries(&read_source(&content, SourceFormat::Fixed));
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].text,
+            format!("05  Ws-Msg pic x(40) value \"ABCDEFGHIJ{}KLM\".", " ".repeat(23))
+        );
+    }
+
+    #[test]
+    fn test_free_format_and_directives() {
+        let content = "\
+01 Rec. *> free from the start
+*> a comment
+   05 Rec-Id pic x(4).
+       >>SOURCE FORMAT IS FIXED
+000100     05  Rec-Name pic x(20).
+      >>SOURCE FREE
+05 Rec-Tail pic x.
+";
+        let lines = read_source(content, SourceFormat::Free);
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.trim()).collect();
+
+        assert_eq!(
+            texts,
+            vec!["01 Rec. *> free from the start", "05 Rec-Id pic x(4).", "05  Rec-Name pic x(20).", "05 Rec-Tail pic x."]
+        );
+        assert_eq!(lines[2].number, 5);
+    }
+
+    #[test]
+    fn test_tabs_expand_to_area_a() {
+        let lines = read_source("\t01  Rec.\n", SourceFormat::Fixed);
+        assert_eq!(lines[0].text, " 01  Rec.");
+    }
+}